@@ -1,19 +1,106 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
 use anyhow::{Ok, Result};
+use aoc2022::queue::TopK;
 use itertools::Itertools;
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day01.txt")?;
-    let start = std::time::Instant::now();
+/// Sums the groups line by line, keeping only the `k` largest sums.
+fn top_group_sums<R: BufRead>(mut reader: R, k: usize) -> Result<TopK<i64>> {
+    let mut top = TopK::new(k);
+    let mut line = String::new();
+    let mut group: Option<i64> = None;
+    while reader.read_line(&mut line)? > 0 {
+        let item = line.trim();
+        if item.is_empty() {
+            top.extend(group.take());
+        } else {
+            *group.get_or_insert(0) += item.parse::<i64>()?;
+        }
+        line.clear();
+    }
+    top.extend(group);
+    Ok(top)
+}
 
+/// Parts 1 and 2, reading the input incrementally.
+fn answers_streaming<R: BufRead>(reader: R) -> Result<(i64, i64)> {
+    let top = top_group_sums(reader, 3)?.into_sorted_vec();
+    Ok((top.first().copied().unwrap_or(0), top.iter().sum()))
+}
+
+/// Parts 1 and 2 from the whole input in memory.
+fn answers(input: &str) -> Result<(i64, i64)> {
     let groups = input.lines().map(|l| l.trim()).group_by(|l| l.len() > 0);
     let nonempty_groups = groups.into_iter().filter_map(|(b, g)| b.then_some(g));
     let mut sums: Vec<i64> = nonempty_groups
         .map(|g| g.map(|l| l.parse::<i64>()).fold_ok(0, |a, b| a + b))
         .try_collect()?;
-    sums.select_nth_unstable_by_key(2, |s| std::cmp::Reverse(*s));
+    let k = sums.len().min(3);
+    if k > 0 {
+        sums.select_nth_unstable_by_key(k - 1, |s| std::cmp::Reverse(*s));
+    }
+    let top = &sums[..k];
+    Ok((top.iter().copied().max().unwrap_or(0), top.iter().sum()))
+}
 
-    println!("part1: {}", sums[..3].iter().copied().max().unwrap_or(0));
-    println!("part2: {}", sums[..3].iter().copied().sum::<i64>());
+fn main() -> Result<()> {
+    // With `stream [path]` the input is read incrementally, `-` reads from stdin.
+    let mut args = std::env::args().skip(1);
+    let (part1, part2, start) = if args.next().as_deref() == Some("stream") {
+        let start = std::time::Instant::now();
+        let path = args
+            .next()
+            .unwrap_or_else(|| "inputs/day01.txt".to_string());
+        let (part1, part2) = match path.as_str() {
+            "-" => answers_streaming(std::io::stdin().lock())?,
+            _ => answers_streaming(BufReader::new(File::open(path)?))?,
+        };
+        (part1, part2, start)
+    } else {
+        let input = std::fs::read_to_string("inputs/day01.txt")?;
+        let start = std::time::Instant::now();
+        let (part1, part2) = answers(&input)?;
+        (part1, part2, start)
+    };
+
+    println!("part1: {part1}");
+    println!("part2: {part2}");
     println!("time: {:?}", start.elapsed());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn streaming_matches_in_memory() {
+        let mut rng = StdRng::seed_from_u64(41);
+        for _ in 0..300 {
+            let mut input = String::new();
+            for _ in 0..rng.gen_range(0..8) {
+                for _ in 0..rng.gen_range(1..5) {
+                    input += &format!("{}\n", rng.gen_range(0..10000));
+                }
+                input.push('\n');
+            }
+            // Half the inputs end without a blank line or even a newline.
+            if rng.gen() {
+                input.truncate(input.trim_end().len());
+            }
+            let expected = answers(&input).unwrap();
+            let reader = BufReader::with_capacity(8, input.as_bytes());
+            assert_eq!(answers_streaming(reader).unwrap(), expected, "{input:?}");
+        }
+        let sample = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+        assert_eq!(answers(sample).unwrap(), (24000, 45000));
+        assert_eq!(
+            answers_streaming(sample.as_bytes()).unwrap(),
+            (24000, 45000)
+        );
+    }
+}
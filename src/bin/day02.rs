@@ -1,29 +1,160 @@
-use anyhow::{Ok, Result};
-use aoc2022::{OptionSomeExt, RegexExtract};
-use regex::Regex;
+use anyhow::{bail, ensure, Context, Ok, Result};
+use itertools::Itertools;
+use serde::Deserialize;
 
-// 0 = Rock, 1 = Paper, 2 = Scissor, (k + 1) mod 3 thus defeats k.
-// 0 = Defeat, 1 = Draw, 2 = Victory
-// Identity: 1 + ours - theirs = outcome   (mod 3)
+const LOSS: usize = 0;
+const DRAW: usize = 1;
+const WIN: usize = 2;
+
+/// How our column is read.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Column {
+    /// The letters name our shapes, in shape order.
+    Shape,
+    /// The first three letters mean we must lose, draw or win.
+    Outcome,
+}
+
+/// A rock-paper-scissors variant, read from JSON. Shapes are numbered in the order of
+/// `shape_scores`, their number is the number of shapes.
+#[derive(Debug, Deserialize)]
+struct Rules {
+    /// The letters of the opponent's column, in shape order.
+    theirs: String,
+    /// The letters of our column.
+    ours: String,
+    shape_scores: Vec<u64>,
+    /// The scores of a loss, draw and win.
+    outcome_scores: [u64; 3],
+    /// (winner, loser) pairs, shapes that don't beat each other draw.
+    beats: Vec<(usize, usize)>,
+    /// How our column is read in each part of the puzzle.
+    parts: Vec<Column>,
+}
+
+impl Default for Rules {
+    // 0 = Rock, 1 = Paper, 2 = Scissor, (k + 1) mod 3 thus defeats k.
+    fn default() -> Self {
+        Self {
+            theirs: "ABC".to_string(),
+            ours: "XYZ".to_string(),
+            shape_scores: vec![1, 2, 3],
+            outcome_scores: [0, 3, 6],
+            beats: vec![(1, 0), (2, 1), (0, 2)],
+            parts: vec![Column::Shape, Column::Outcome],
+        }
+    }
+}
+
+fn letters(column: &str) -> Result<Vec<char>> {
+    let letters = column.chars().collect_vec();
+    ensure!(letters.iter().all_unique(), "repeated letter in {column:?}");
+    Ok(letters)
+}
+
+fn letter_index(letters: &[char], letter: char) -> Result<usize> {
+    let index = letters.iter().position(|&c| c == letter);
+    index.with_context(|| format!("unknown letter {letter:?}"))
+}
+
+/// Scores per part, indexed by the letters of a line. `None` marks impossible lines.
+struct ScoreTable {
+    theirs: Vec<char>,
+    ours: Vec<char>,
+    scores: Vec<Vec<Option<u64>>>,
+}
+
+impl ScoreTable {
+    fn new(rules: &Rules) -> Result<Self> {
+        let n = rules.shape_scores.len();
+        let (theirs, ours) = (letters(&rules.theirs)?, letters(&rules.ours)?);
+        ensure!(
+            theirs.len() == n,
+            "expected {n} letters in {:?}",
+            rules.theirs
+        );
+
+        let mut outcome = vec![vec![DRAW; n]; n];
+        for &(winner, loser) in &rules.beats {
+            ensure!(winner < n && loser < n, "no shape {winner} or {loser}");
+            ensure!(winner != loser, "shape {winner} beats itself");
+            ensure!(
+                outcome[winner][loser] != LOSS,
+                "{winner} and {loser} beat each other"
+            );
+            outcome[winner][loser] = WIN;
+            outcome[loser][winner] = LOSS;
+        }
+
+        let score = |ours: usize, theirs: usize| {
+            rules.shape_scores[ours] + rules.outcome_scores[outcome[ours][theirs]]
+        };
+        let scores = rules.parts.iter().map(|column| {
+            let cells = (0..n).cartesian_product(0..ours.len());
+            cells.map(|(t, o)| match *column {
+                Column::Shape => (o < n).then(|| score(o, t)),
+                // Pick the best scoring shape if several give the outcome.
+                Column::Outcome => (0..n)
+                    .filter(|&s| o < 3 && outcome[s][t] == o)
+                    .map(|s| score(s, t))
+                    .max(),
+            })
+        });
+
+        Ok(Self {
+            scores: scores.map(|part| part.collect()).collect(),
+            theirs,
+            ours,
+        })
+    }
+
+    fn score_line(&self, line: &str) -> Result<Vec<u64>> {
+        let (t, o) = line
+            .chars()
+            .collect_tuple()
+            .and_then(|(t, space, o)| (space == ' ').then_some((t, o)))
+            .with_context(|| format!("expected two letters in {line:?}"))?;
+        let t = letter_index(&self.theirs, t)?;
+        let o = letter_index(&self.ours, o)?;
+        let cell = t * self.ours.len() + o;
+        let scores = self
+            .scores
+            .iter()
+            .enumerate()
+            .map(|(part, scores)| match scores[cell] {
+                Some(score) => Ok(score),
+                None => bail!("no way to play {line:?} in part {}", part + 1),
+            });
+        scores.try_collect()
+    }
+}
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day02.txt")?;
+    // Pass a JSON rules file, and optionally an input, to play a variant. See `Rules` for
+    // the fields.
+    let mut args = std::env::args().skip(1);
+    let rules = match args.next() {
+        Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+        None => Rules::default(),
+    };
+    let input_path = args
+        .next()
+        .unwrap_or_else(|| "inputs/day02.txt".to_string());
+    let input = std::fs::read_to_string(input_path)?;
     let start = std::time::Instant::now();
 
-    let mut part1 = 0;
-    let mut part2 = 0;
-    let re = Regex::new("([ABC]) ([XYZ])")?;
+    let table = ScoreTable::new(&rules)?;
+    let mut totals = vec![0; rules.parts.len()];
     for line in input.lines() {
-        let [abc, xyz] = re.extract(line).some()?.1;
-        let [abc, xyz] = [abc.as_bytes()[0] - b'A', xyz.as_bytes()[0] - b'X'];
-        let p1_outcome = (1 + xyz + (3 - abc)) % 3;
-        let p2_shape = (xyz + abc + (3 - 1)) % 3;
-        part1 += (1 + xyz + 3 * p1_outcome) as u64;
-        part2 += (1 + p2_shape + 3 * xyz) as u64;
+        for (total, score) in totals.iter_mut().zip(table.score_line(line)?) {
+            *total += score;
+        }
     }
 
-    println!("part1: {part1}");
-    println!("part2: {part2}");
+    for (part, total) in totals.iter().enumerate() {
+        println!("part{}: {total}", part + 1);
+    }
     println!("time: {:?}", start.elapsed());
     Ok(())
 }
@@ -1,4 +1,7 @@
-use anyhow::{Ok, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use anyhow::{bail, Ok, Result};
 
 /*
     We use the fact that each line (including the newline) consists of 4 bytes.
@@ -20,23 +23,311 @@ use anyhow::{Ok, Result};
     entries [0, 8) and thus fitting in 3 bits each packed inside a single u32.
 */
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day02.txt")?;
-    let start = std::time::Instant::now();
-
-    let ints: &[u32] = bytemuck::cast_slice(input.as_bytes());
-    let mut part1 = ints.len() as u32;
-    let mut part2 = ints.len() as u32;
-    for u in ints {
-        let u = u.from_le();
+/// Scores a block of at most 2^24 whole lines, so the u32 sums cannot overflow.
+type Kernel = fn(&[u8]) -> (u32, u32);
+
+const BLOCK_LEN: usize = 4 << 24;
+
+fn score_scalar(block: &[u8]) -> (u32, u32) {
+    let (mut part1, mut part2) = (0, 0);
+    for line in block.chunks_exact(4) {
+        let u = u32::from_le_bytes(line.try_into().unwrap());
         let o = u.wrapping_mul(1887065750_u32) >> 27;
-        part1 += (*u == 173678658) as u32 + ((475903013 >> o) & 7);
-        part2 += (*u == 173678658) as u32 + ((224201846 >> o) & 7);
+        part1 += 1 + (u == 173678658) as u32 + ((475903013 >> o) & 7);
+        part2 += 1 + (u == 173678658) as u32 + ((224201846 >> o) & 7);
+    }
+    (part1, part2)
+}
+
+/*
+    SSE2 has neither a 32-bit multiply nor per-lane shifts, so it computes the scores
+    directly from the shapes, 4 lines per vector and 16 lines per iteration:
+    with a = abc - 'A' and x = xyz - 'X',
+        p1 = 1 + x + 3 * ((4 + x - a) mod 3),  p2 = 1 + ((2 + a + x) mod 3) + 3 * x.
+    AVX2 evaluates the same hash and packed table as the scalar loop, 8 lines per vector.
+*/
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    fn horizontal_sum<const N: usize>(v: [u32; N]) -> u32 {
+        v.iter().sum()
+    }
+
+    /// Reduces lanes in 2..=6 modulo 3.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn mod3(t: __m128i) -> __m128i {
+        let three = _mm_set1_epi32(3);
+        let sub3_above = |t, n| {
+            let above = _mm_cmpgt_epi32(t, _mm_set1_epi32(n));
+            _mm_sub_epi32(t, _mm_and_si128(above, three))
+        };
+        sub3_above(sub3_above(t, 5), 2)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn scores_sse2(v: __m128i) -> (__m128i, __m128i) {
+        let byte = _mm_set1_epi32(0xff);
+        let a = _mm_sub_epi32(_mm_and_si128(v, byte), _mm_set1_epi32(b'A' as i32));
+        let x = _mm_and_si128(_mm_srli_epi32(v, 16), byte);
+        let x = _mm_sub_epi32(x, _mm_set1_epi32(b'X' as i32));
+        let times3 = |t| _mm_add_epi32(_mm_add_epi32(t, t), t);
+        let one_x = _mm_add_epi32(_mm_set1_epi32(1), x);
+
+        let outcome = mod3(_mm_sub_epi32(_mm_add_epi32(x, _mm_set1_epi32(4)), a));
+        let p1 = _mm_add_epi32(one_x, times3(outcome));
+        let shape = mod3(_mm_add_epi32(_mm_add_epi32(a, x), _mm_set1_epi32(2)));
+        let p2 = _mm_add_epi32(_mm_add_epi32(_mm_set1_epi32(1), shape), times3(x));
+        (p1, p2)
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn score_sse2(block: &[u8]) -> (u32, u32) {
+        let mut sums = [_mm_setzero_si128(); 2];
+        let mut chunks = block.chunks_exact(64);
+        for chunk in &mut chunks {
+            for i in 0..4 {
+                let v = _mm_loadu_si128(chunk.as_ptr().add(16 * i) as *const __m128i);
+                let (p1, p2) = scores_sse2(v);
+                sums[0] = _mm_add_epi32(sums[0], p1);
+                sums[1] = _mm_add_epi32(sums[1], p2);
+            }
+        }
+        let (rest1, rest2) = super::score_scalar(chunks.remainder());
+        let [sum1, sum2] = sums.map(|s| {
+            let mut lanes = [0u32; 4];
+            _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, s);
+            horizontal_sum(lanes)
+        });
+        (sum1 + rest1, sum2 + rest2)
     }
 
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn score_avx2(block: &[u8]) -> (u32, u32) {
+        let hash = _mm256_set1_epi32(1887065750);
+        let tables = [_mm256_set1_epi32(475903013), _mm256_set1_epi32(224201846)];
+        let (bzbz, seven, one) = (
+            _mm256_set1_epi32(173678658),
+            _mm256_set1_epi32(7),
+            _mm256_set1_epi32(1),
+        );
+        let mut sums = [_mm256_setzero_si256(); 2];
+        let mut chunks = block.chunks_exact(64);
+        for chunk in &mut chunks {
+            for i in 0..2 {
+                let v = _mm256_loadu_si256(chunk.as_ptr().add(32 * i) as *const __m256i);
+                let o = _mm256_srli_epi32(_mm256_mullo_epi32(v, hash), 27);
+                // The comparison mask is -1 where equal, subtracting it adds one.
+                let base = _mm256_sub_epi32(one, _mm256_cmpeq_epi32(v, bzbz));
+                for (sum, table) in sums.iter_mut().zip(tables) {
+                    let score = _mm256_and_si256(_mm256_srlv_epi32(table, o), seven);
+                    *sum = _mm256_add_epi32(*sum, _mm256_add_epi32(base, score));
+                }
+            }
+        }
+        let (rest1, rest2) = super::score_scalar(chunks.remainder());
+        let [sum1, sum2] = sums.map(|s| {
+            let mut lanes = [0u32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, s);
+            horizontal_sum(lanes)
+        });
+        (sum1 + rest1, sum2 + rest2)
+    }
+}
+
+/// The fastest kernel the CPU supports, with the scalar loop as portable fallback.
+fn select_kernel() -> (&'static str, Kernel) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safety: AVX2 support was just checked.
+            return ("avx2", |block| unsafe { x86::score_avx2(block) });
+        }
+        if is_x86_feature_detected!("sse2") {
+            // Safety: SSE2 support was just checked.
+            return ("sse2", |block| unsafe { x86::score_sse2(block) });
+        }
+    }
+    ("scalar", score_scalar)
+}
+
+struct Scores {
+    kernel: Kernel,
+    part1: u64,
+    part2: u64,
+}
+
+impl Scores {
+    fn new(kernel: Kernel) -> Self {
+        Self {
+            kernel,
+            part1: 0,
+            part2: 0,
+        }
+    }
+
+    /// Scores whole lines, `bytes.len()` must be a multiple of 4.
+    fn add_lines(&mut self, bytes: &[u8]) {
+        for block in bytes.chunks(BLOCK_LEN) {
+            let (part1, part2) = (self.kernel)(block);
+            self.part1 += part1 as u64;
+            self.part2 += part2 as u64;
+        }
+    }
+
+    /// Scores the bytes after the last whole line, a last line without its newline.
+    fn add_tail(&mut self, tail: &[u8]) -> Result<()> {
+        match *tail {
+            [] => {},
+            [a, b, c] => self.add_lines(&[a, b, c, b'\n']),
+            _ => bail!("truncated line {:?}", String::from_utf8_lossy(tail)),
+        }
+        Ok(())
+    }
+}
+
+/// Scores the input chunk by chunk, carrying lines split across chunks over.
+fn scores_streaming<R: BufRead>(mut reader: R, kernel: Kernel) -> Result<Scores> {
+    let mut scores = Scores::new(kernel);
+    let mut carry = [0; 4];
+    let mut carried = 0;
+    loop {
+        let chunk = reader.fill_buf()?;
+        let chunk_len = chunk.len();
+        if chunk_len == 0 {
+            break;
+        }
+
+        let mut rest = chunk;
+        if carried > 0 {
+            let take = (4 - carried).min(rest.len());
+            carry[carried..carried + take].copy_from_slice(&rest[..take]);
+            (carried, rest) = (carried + take, &rest[take..]);
+            if carried == 4 {
+                scores.add_lines(&carry);
+                carried = 0;
+            }
+        }
+        let whole = rest.len() / 4 * 4;
+        scores.add_lines(&rest[..whole]);
+        let tail = &rest[whole..];
+        if !tail.is_empty() {
+            carry[..tail.len()].copy_from_slice(tail);
+            carried = tail.len();
+        }
+        reader.consume(chunk_len);
+    }
+    scores.add_tail(&carry[..carried])?;
+    Ok(scores)
+}
+
+fn main() -> Result<()> {
+    // With `stream [path]` the input is read incrementally, `-` reads from stdin.
+    let mut args = std::env::args().skip(1);
+    let (kernel_name, kernel) = select_kernel();
+    let (start, scores) = if args.next().as_deref() == Some("stream") {
+        let start = std::time::Instant::now();
+        let path = args
+            .next()
+            .unwrap_or_else(|| "inputs/day02.txt".to_string());
+        let scores = match path.as_str() {
+            "-" => scores_streaming(std::io::stdin().lock(), kernel)?,
+            _ => scores_streaming(BufReader::new(File::open(path)?), kernel)?,
+        };
+        (start, scores)
+    } else {
+        let input = std::fs::read_to_string("inputs/day02.txt")?;
+        let start = std::time::Instant::now();
+        let bytes = input.as_bytes();
+        let whole = bytes.len() / 4 * 4;
+        let mut scores = Scores::new(kernel);
+        scores.add_lines(&bytes[..whole]);
+        scores.add_tail(&bytes[whole..])?;
+        (start, scores)
+    };
+
     let time = start.elapsed();
-    println!("part1: {part1}");
-    println!("part2: {part2}");
-    println!("time: {:?}", time);
+    println!("part1: {}", scores.part1);
+    println!("part2: {}", scores.part2);
+    println!("time: {:?} ({kernel_name})", time);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn random_input(rng: &mut StdRng, lines: usize) -> Vec<u8> {
+        let line = |_| {
+            [
+                b'A' + rng.gen_range(0..3),
+                b' ',
+                b'X' + rng.gen_range(0..3),
+                b'\n',
+            ]
+        };
+        (0..lines).flat_map(line).collect()
+    }
+
+    fn kernels() -> Vec<Kernel> {
+        let mut kernels: Vec<Kernel> = vec![score_scalar];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("sse2") {
+                kernels.push(|block| unsafe { x86::score_sse2(block) });
+            }
+            if is_x86_feature_detected!("avx2") {
+                kernels.push(|block| unsafe { x86::score_avx2(block) });
+            }
+        }
+        kernels
+    }
+
+    #[test]
+    fn scalar_matches_rules() {
+        let input = random_input(&mut StdRng::seed_from_u64(2), 1000);
+        let (mut part1, mut part2) = (0, 0);
+        for line in input.chunks(4) {
+            let [abc, xyz] = [(line[0] - b'A') as u32, (line[2] - b'X') as u32];
+            part1 += 1 + xyz + 3 * ((4 + xyz - abc) % 3);
+            part2 += 1 + (2 + abc + xyz) % 3 + 3 * xyz;
+        }
+        assert_eq!(score_scalar(&input), (part1, part2));
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..200 {
+            let lines = rng.gen_range(0..300);
+            let input = random_input(&mut rng, lines);
+            let expected = score_scalar(&input);
+            for kernel in kernels() {
+                assert_eq!(kernel(&input), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn streaming_matches_in_memory() {
+        let mut input = random_input(&mut StdRng::seed_from_u64(2), 5000);
+        input.pop();
+        let mut whole = Scores::new(score_scalar);
+        whole.add_lines(&input[..input.len() / 4 * 4]);
+        whole.add_tail(&input[input.len() / 4 * 4..]).unwrap();
+        for kernel in kernels() {
+            let reader = BufReader::with_capacity(37, input.as_slice());
+            let streamed = scores_streaming(reader, kernel).unwrap();
+            assert_eq!((streamed.part1, streamed.part2), (whole.part1, whole.part2));
+        }
+    }
+}
@@ -1,28 +1,177 @@
-use anyhow::{Ok, Result};
+use std::fmt;
+
+use anyhow::{bail, ensure, Result};
+use aoc2022::bitset::BitSet;
 use itertools::Itertools;
 
-fn rucksack_bitset(rucksack: &str) -> u64 {
-    let priorities = rucksack.bytes().map(|item| match item {
-        b'a'..=b'z' => item - b'a' + 1,
-        b'A'..=b'Z' => item - b'A' + 27,
-        _ => panic!("unknown item"),
-    });
-    priorities.map(|p| 1u64 << p).fold(0, |a, b| a | b)
+type Items = BitSet<1>;
+
+/// Problems with the rucksack on line `line` (1-based), or the group starting there.
+#[derive(Debug)]
+enum RucksackError {
+    BadItem {
+        line: usize,
+        column: usize,
+        byte: u8,
+    },
+    OddLength {
+        line: usize,
+        len: usize,
+    },
+    NoCommonItem {
+        line: usize,
+        group: bool,
+    },
+    MultipleCommonItems {
+        line: usize,
+        group: bool,
+        items: String,
+    },
+    IncompleteGroup {
+        line: usize,
+        size: usize,
+    },
+}
+
+impl std::error::Error for RucksackError {}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = |group: bool| if group { "group" } else { "compartments" };
+        match self {
+            Self::BadItem { line, column, byte } => {
+                let byte = std::ascii::escape_default(*byte);
+                write!(f, "line {line}, column {column}: bad item '{byte}'")
+            },
+            Self::OddLength { line, len } => {
+                write!(
+                    f,
+                    "line {line}: odd length {len} does not split in two compartments"
+                )
+            },
+            Self::NoCommonItem { line, group } => {
+                write!(f, "line {line}: {} share no item", what(*group))
+            },
+            Self::MultipleCommonItems { line, group, items } => {
+                write!(
+                    f,
+                    "line {line}: {} share several items {items:?}",
+                    what(*group)
+                )
+            },
+            Self::IncompleteGroup { line, size } => {
+                write!(
+                    f,
+                    "line {line}: trailing group of {size} rucksacks, expected 3"
+                )
+            },
+        }
+    }
+}
+
+fn item_char(priority: usize) -> char {
+    match priority {
+        1..=26 => (b'a' + priority as u8 - 1) as char,
+        _ => (b'A' + priority as u8 - 27) as char,
+    }
+}
+
+/// Parses the items starting at 1-based `column` of line `line`.
+fn rucksack_items(rucksack: &str, line: usize, column: usize) -> Result<Items, RucksackError> {
+    let mut items = Items::new();
+    for (i, byte) in rucksack.bytes().enumerate() {
+        let priority = match byte {
+            b'a'..=b'z' => byte - b'a' + 1,
+            b'A'..=b'Z' => byte - b'A' + 27,
+            _ => {
+                let column = column + i;
+                return Err(RucksackError::BadItem { line, column, byte });
+            },
+        };
+        items.insert(priority as usize);
+    }
+    Ok(items)
+}
+
+/// The priority of the only item in `common`.
+fn single_item(common: Items, line: usize, group: bool) -> Result<usize, RucksackError> {
+    match common.len() {
+        0 => Err(RucksackError::NoCommonItem { line, group }),
+        1 => Ok(common.first().unwrap()),
+        _ => {
+            let items = common.iter().map(item_char).collect();
+            Err(RucksackError::MultipleCommonItems { line, group, items })
+        },
+    }
+}
+
+fn compartments_priority(rucksack: &str, line: usize) -> Result<usize, RucksackError> {
+    let len = rucksack.len();
+    if len % 2 == 1 {
+        return Err(RucksackError::OddLength { line, len });
+    }
+    let (a, b) = rucksack.split_at(len / 2);
+    let common = rucksack_items(a, line, 1)? & rucksack_items(b, line, len / 2 + 1)?;
+    single_item(common, line, false)
+}
+
+fn group_priority(group: &[(usize, &str)]) -> Result<usize, RucksackError> {
+    let line = group[0].0;
+    if group.len() < 3 {
+        let size = group.len();
+        return Err(RucksackError::IncompleteGroup { line, size });
+    }
+    let mut common = !Items::new();
+    for &(line, rucksack) in group {
+        common &= rucksack_items(rucksack, line, 1)?;
+    }
+    single_item(common, line, true)
+}
+
+/// Sums the priorities, stopping at the first error unless `skipped` collects them.
+fn sum_priorities(
+    part: usize,
+    priorities: impl Iterator<Item = Result<usize, RucksackError>>,
+    skipped: Option<&mut Vec<(usize, RucksackError)>>,
+) -> Result<usize> {
+    match skipped {
+        Some(skipped) => {
+            let (ok, errors): (Vec<_>, Vec<_>) = priorities.partition_result();
+            skipped.extend(errors.into_iter().map(|error| (part, error)));
+            Ok(ok.iter().sum())
+        },
+        None => Ok(priorities.sum::<Result<usize, _>>()?),
+    }
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day03.txt")?;
+    // With `--lenient`, bad lines and groups are skipped and reported instead of failing.
+    let (flags, paths): (Vec<_>, Vec<_>) =
+        std::env::args().skip(1).partition(|a| a.starts_with("--"));
+    if let Some(flag) = flags.iter().find(|flag| *flag != "--lenient") {
+        bail!("unknown flag {flag}, usage: day03 [--lenient] [path]");
+    }
+    ensure!(paths.len() <= 1, "usage: day03 [--lenient] [path]");
+    let lenient = !flags.is_empty();
+    let path = paths.first().map_or("inputs/day03.txt", |p| p.as_str());
+    let input = std::fs::read_to_string(path)?;
     let start = std::time::Instant::now();
 
-    let part1_common = input.lines().map(|line| {
-        let (a, b) = line.split_at(line.len() / 2);
-        (rucksack_bitset(a) & rucksack_bitset(b)).trailing_zeros()
-    });
-    let part2_groups = input.lines().map(rucksack_bitset).tuples();
-    let part2_common = part2_groups.map(|(a, b, c)| (a & b & c).trailing_zeros());
+    let lines = input.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let mut skipped = lenient.then(Vec::new);
+    let part1_priorities = lines
+        .clone()
+        .map(|(i, line)| compartments_priority(line, i));
+    let part1 = sum_priorities(1, part1_priorities, skipped.as_mut())?;
+    let groups = lines.chunks(3);
+    let part2_priorities = groups.into_iter().map(|g| group_priority(&g.collect_vec()));
+    let part2 = sum_priorities(2, part2_priorities, skipped.as_mut())?;
 
-    println!("part1: {}", part1_common.sum::<u32>());
-    println!("part2: {}", part2_common.sum::<u32>());
+    println!("part1: {part1}");
+    println!("part2: {part2}");
     println!("time: {:?}", start.elapsed());
+    for (part, error) in skipped.iter().flatten() {
+        eprintln!("part{part}: skipped {error}");
+    }
     Ok(())
 }
@@ -1,23 +1,184 @@
-use anyhow::{Ok, Result};
-use aoc2022::{OptionSomeExt, RegexExtract};
-use regex::Regex;
+use anyhow::{ensure, Context, Ok, Result};
+use aoc2022::interval::IntervalSet;
+use itertools::Itertools;
+
+/// Largest accepted section number, so that one past every range end fits in a `u64`.
+const MAX_SECTION: u64 = u64::MAX - 1;
+
+/// The section assignments of one line, any number of comma-separated `start-end` ranges.
+struct Group {
+    ranges: Vec<(u64, u64)>,
+    sets: Vec<IntervalSet<u64>>,
+}
+
+impl Group {
+    fn parse(line: &str) -> Result<Self> {
+        let range = |r: &str| {
+            let (start, end) = r
+                .split_once('-')
+                .with_context(|| format!("bad range {r:?}"))?;
+            let (start, end): (u64, u64) = (start.parse()?, end.parse()?);
+            ensure!(start <= end, "reversed range {r:?}");
+            ensure!(
+                end <= MAX_SECTION,
+                "range {r:?} ends past section {MAX_SECTION}"
+            );
+            Ok((start, end))
+        };
+        let ranges: Vec<_> = line.split(',').map(range).try_collect()?;
+        let sets = ranges
+            .iter()
+            .map(|&(s, e)| IntervalSet::from(s..=e))
+            .collect();
+        Ok(Self { ranges, sets })
+    }
+
+    /// Edges (i, j) for range i containing range j. Equal ranges only point from the first
+    /// to the later ones, so there are no cycles.
+    fn containment_dag(&self) -> Vec<(usize, usize)> {
+        let pairs = (0..self.sets.len()).cartesian_product(0..self.sets.len());
+        let contains = |&(i, j): &(usize, usize)| {
+            let (outer, inner) = (&self.sets[i], &self.sets[j]);
+            i != j && inner.is_subset(outer) && (i < j || outer != inner)
+        };
+        pairs.filter(contains).collect()
+    }
+
+    /// Whether one range contains all the others.
+    fn has_outermost(&self) -> bool {
+        self.sets
+            .iter()
+            .any(|outer| self.sets.iter().all(|s| s.is_subset(outer)))
+    }
+
+    fn any_overlap(&self) -> bool {
+        let mut pairs = self.sets.iter().tuple_combinations();
+        pairs.any(|(a, b): (&IntervalSet<u64>, _)| !a.intersection(b).is_empty())
+    }
+
+    /// The sections in every range.
+    fn intersection(&self) -> IntervalSet<u64> {
+        let common = self.sets.iter().cloned().reduce(|a, b| a.intersection(&b));
+        common.unwrap_or_default()
+    }
+
+    /// The sections in any range.
+    fn union(&self) -> IntervalSet<u64> {
+        self.sets.iter().fold(IntervalSet::new(), |a, b| a.union(b))
+    }
+}
+
+fn format_set(set: &IntervalSet<u64>) -> String {
+    match set.is_empty() {
+        true => "none".to_string(),
+        false => set
+            .iter_inclusive()
+            .map(|r| format!("{}-{}", r.start(), r.end()))
+            .join(","),
+    }
+}
+
+/// The largest number of ranges covering a section, and the sections covered that often.
+fn most_covered(groups: &[Group]) -> (usize, IntervalSet<u64>) {
+    // Sweep over range starts and ends, the count holds up to the next event.
+    let ranges = groups.iter().flat_map(|g| &g.ranges);
+    let events = ranges
+        .flat_map(|&(s, e)| [(s, 1), (e + 1, -1)])
+        .sorted_unstable();
+    let (mut count, mut best, mut sections) = (0, 0, IntervalSet::new());
+    let mut prev = 0;
+    for (pos, deltas) in &events.group_by(|(pos, _)| *pos) {
+        if count > 0 && count == best {
+            sections.insert(prev..pos);
+        }
+        count += deltas.map(|(_, delta)| delta).sum::<i64>();
+        if count > best {
+            (best, sections) = (count, IntervalSet::new());
+        }
+        prev = pos;
+    }
+    (best as usize, sections)
+}
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day04.txt")?;
+    // `report` prints the analysis of every line, `hottest` the most covered sections. Any
+    // other first argument is the input path.
+    let mut args = std::env::args().skip(1).peekable();
+    let mode = args.next_if(|a| a == "report" || a == "hottest");
+    let path = args
+        .next()
+        .unwrap_or_else(|| "inputs/day04.txt".to_string());
+    ensure!(
+        args.next().is_none(),
+        "usage: day04 [report|hottest] [path]"
+    );
+    let input = std::fs::read_to_string(&path).with_context(|| format!("reading {path}"))?;
     let start = std::time::Instant::now();
 
-    let mut part1 = 0;
-    let mut part2 = 0;
-    let re = Regex::new(r"(\d+)-(\d+),(\d+)-(\d+)")?;
-    for line in input.lines() {
-        let assignment = re.extract(line).some()?.1;
-        let [s1, e1, s2, e2] = assignment.map(|id| id.parse::<u64>().unwrap());
-        part1 += (s1 <= s2 && e2 <= e1 || s2 <= s1 && e1 <= e2) as u64;
-        part2 += (s1 <= e2 && s2 <= e1) as u64;
+    let groups: Vec<Group> = input.lines().map(Group::parse).try_collect()?;
+    match mode.as_deref() {
+        Some("report") => {
+            for (i, group) in groups.iter().enumerate() {
+                let dag = group
+                    .containment_dag()
+                    .iter()
+                    .map(|(a, b)| format!("{a}>{b}"))
+                    .join(" ");
+                let union = group.union();
+                println!(
+                    "line {}: contains [{dag}], intersection {}, union {} ({} sections)",
+                    i + 1,
+                    format_set(&group.intersection()),
+                    format_set(&union),
+                    union.covered_len(),
+                );
+            }
+        },
+        Some("hottest") => {
+            let (count, sections) = most_covered(&groups);
+            println!("covered {count} times: {}", format_set(&sections));
+        },
+        _ => {
+            let part1 = groups.iter().filter(|g| g.has_outermost()).count();
+            let part2 = groups.iter().filter(|g| g.any_overlap()).count();
+            println!("part1: {part1}");
+            println!("part2: {part2}");
+        },
     }
-
-    println!("part1: {part1}");
-    println!("part2: {part2}");
     println!("time: {:?}", start.elapsed());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_ranges() {
+        let errors = [
+            ("1-10,5-3", "reversed range \"5-3\""),
+            ("1-18446744073709551615", "ends past section"),
+            ("1-2,3", "bad range \"3\""),
+        ];
+        for (line, message) in errors {
+            let error = Group::parse(line).err().unwrap().to_string();
+            assert!(
+                error.contains(message),
+                "{error:?} should contain {message:?}"
+            );
+        }
+        let group = Group::parse("0-18446744073709551614").unwrap();
+        assert_eq!(most_covered(&[group]).0, 1);
+    }
+
+    #[test]
+    fn hottest_sections() {
+        let groups: Vec<Group> = ["2-4,6-8", "2-3,4-5", "5-7,7-9", "2-8,3-7"]
+            .into_iter()
+            .map(Group::parse)
+            .try_collect()
+            .unwrap();
+        let (count, sections) = most_covered(&groups);
+        assert_eq!((count, format_set(&sections)), (5, "7-7".to_string()));
+    }
+}
@@ -1,44 +1,574 @@
-use anyhow::{Ok, Result};
+use std::fmt::{self, Write};
+
+use anyhow::{bail, ensure, Context, Ok, Result};
+use aoc2022::rope::{Rope, Ropes};
 use aoc2022::{GetDisjointMut, OptionSomeExt, RegexExtract};
 use itertools::Itertools;
+use rand::Rng;
 use regex::Regex;
+use serde::Serialize;
+
+type Stacks = Vec<Vec<u8>>;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Crane {
+    /// Moves crates one at a time, reversing their order.
+    Mover9000,
+    /// Moves crates all at once.
+    Mover9001,
+}
 
-fn stack_heads(mut stacks: Vec<Vec<u8>>, instructions: &[&str], reverse: bool) -> Result<String> {
+struct Move<'a> {
+    count: usize,
+    from: usize,
+    to: usize,
+    line: &'a str,
+    index: usize,
+}
+
+impl fmt::Display for Move<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {} ({:?})", self.index + 1, self.line)
+    }
+}
+
+/// Parses instructions given with their line numbers in the input, counted from 1.
+fn parse_moves<'a>(instructions: &[(usize, &'a str)]) -> Result<Vec<Move<'a>>> {
     let re = Regex::new(r"move (\d+) from (\d+) to (\d+)")?;
-    for line in instructions {
-        let instr = re.extract(line).some()?.1;
-        let [len, from, to] = instr.map(|x| x.parse::<usize>().unwrap());
-        let locs = [from.wrapping_sub(1), to.wrapping_sub(1)];
-        let [from_stack, to_stack] = stacks.get_disjoint_mut(locs).some()?;
-        if reverse {
-            to_stack.extend(from_stack.drain(from_stack.len() - len..).rev());
-        } else {
-            to_stack.extend(from_stack.drain(from_stack.len() - len..));
+    let moves = instructions
+        .iter()
+        .enumerate()
+        .map(|(index, &(line_no, line))| {
+            let instr = re.extract(line).some_ctx("expected a move", line);
+            let instr = instr.with_context(|| format!("line {line_no}"))?.1;
+            let [count, from, to] = instr.map(|x| {
+                let n = x.parse::<usize>();
+                n.with_context(|| format!("line {line_no}: invalid number {x:?}"))
+            });
+            let [count, from, to] = [count?, from?, to?];
+            let (from, to) = (from.wrapping_sub(1), to.wrapping_sub(1));
+            Ok(Move {
+                count,
+                from,
+                to,
+                line,
+                index,
+            })
+        });
+    moves.try_collect()
+}
+
+/// Crate stacks that can replay moves. `Stacks` costs O(count) per move, `RopeStacks`
+/// O(log n).
+trait CrateStacks {
+    fn new(stacks: &Stacks) -> Self;
+
+    fn apply(&mut self, mv: &Move, crane: Crane) -> Result<()>;
+
+    /// The top crate of every non-empty stack.
+    fn heads(&self) -> String;
+}
+
+fn ensure_holds(mv: &Move, held: usize) -> Result<()> {
+    ensure!(
+        held >= mv.count,
+        "impossible {mv}: stack {} holds only {held} crates",
+        mv.from + 1
+    );
+    Ok(())
+}
+
+impl CrateStacks for Stacks {
+    fn new(stacks: &Stacks) -> Self {
+        stacks.clone()
+    }
+
+    fn apply(&mut self, mv: &Move, crane: Crane) -> Result<()> {
+        let stack_pair = self.try_get_disjoint_mut([mv.from, mv.to]);
+        let [from_stack, to_stack] = stack_pair.with_context(|| format!("invalid {mv}"))?;
+        ensure_holds(mv, from_stack.len())?;
+        let moved = from_stack.drain(from_stack.len() - mv.count..);
+        match crane {
+            Crane::Mover9000 => to_stack.extend(moved.rev()),
+            Crane::Mover9001 => to_stack.extend(moved),
         }
+        Ok(())
     }
 
-    let stack_heads = stacks.iter().flat_map(|s| s.last());
-    Ok(stack_heads.map(|c| *c as char).collect())
+    fn heads(&self) -> String {
+        self.iter()
+            .flat_map(|s| s.last())
+            .map(|c| *c as char)
+            .collect()
+    }
+}
+
+/// Stacks as implicit treaps in one arena, moving a pile is a split and a merge. The
+/// CrateMover 9000 reverses the pile lazily.
+struct RopeStacks {
+    ropes: Ropes<u8>,
+    stacks: Vec<Rope>,
+}
+
+impl CrateStacks for RopeStacks {
+    fn new(stacks: &Stacks) -> Self {
+        let mut ropes = Ropes::new();
+        let mut rng = rand::thread_rng();
+        let stacks = stacks
+            .iter()
+            .map(|s| ropes.rope(s.iter().copied(), &mut rng))
+            .collect();
+        Self { ropes, stacks }
+    }
+
+    fn apply(&mut self, mv: &Move, crane: Crane) -> Result<()> {
+        let n = self.stacks.len();
+        ensure!(mv.from != mv.to && mv.from.max(mv.to) < n, "invalid {mv}");
+        let from_stack = &mut self.stacks[mv.from];
+        let held = self.ropes.len(from_stack);
+        ensure_holds(mv, held)?;
+        let mut moved = self.ropes.split_off(from_stack, held - mv.count);
+        if crane == Crane::Mover9000 {
+            self.ropes.reverse(&mut moved);
+        }
+        self.ropes.append(&mut self.stacks[mv.to], moved);
+        Ok(())
+    }
+
+    fn heads(&self) -> String {
+        let heads = self.stacks.iter().flat_map(|s| self.ropes.last(s));
+        heads.map(|c| *c as char).collect()
+    }
+}
+
+fn stack_heads<S: CrateStacks>(stacks: &Stacks, moves: &[Move], crane: Crane) -> Result<String> {
+    let mut stacks = S::new(stacks);
+    for mv in moves {
+        stacks.apply(mv, crane)?;
+    }
+    Ok(stacks.heads())
+}
+
+/// Where the diagram puts each stack, to render stacks back in the same format.
+#[derive(Debug)]
+struct Layout {
+    label_row: String,
+    /// The column of each stack's crate letters.
+    columns: Vec<usize>,
+}
+
+impl Layout {
+    /// The stacks in the puzzle's diagram format, including the label row. Rows are padded
+    /// to the width of the label row.
+    fn render(&self, stacks: &Stacks) -> String {
+        let height = stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        let last_column = self.columns.iter().max().map_or(0, |c| c + 2);
+        let width = self.label_row.len().max(last_column);
+        let mut out = String::new();
+        for row in (0..height).rev() {
+            let mut line = vec![b' '; width];
+            for (stack, &column) in stacks.iter().zip(&self.columns) {
+                if let Some(&c) = stack.get(row) {
+                    line[column - 1..column + 2].copy_from_slice(&[b'[', c, b']']);
+                }
+            }
+            out += &String::from_utf8_lossy(&line);
+            out.push('\n');
+        }
+        out += &self.label_row;
+        out.push('\n');
+        out
+    }
+}
+
+/// Parses the diagram lines, the last of which labels the stacks 1 to n. Crates are found
+/// by the label their brackets overlap, so columns don't need to be a multiple of 4.
+fn parse_diagram(lines: &[&str]) -> Result<(Stacks, Layout)> {
+    let (label_row, crate_rows) = lines.split_last().context("missing stack diagram")?;
+    let label_line = lines.len();
+    let mut spans = Vec::new();
+    for (start, token) in tokens(label_row) {
+        let expected = spans.len() + 1;
+        if token.parse::<usize>().ok() != Some(expected) {
+            bail!(
+                "line {label_line}, column {}: expected label {expected}, found {token:?}",
+                start + 1
+            );
+        }
+        spans.push(start..start + token.len());
+    }
+    ensure!(!spans.is_empty(), "line {label_line}: no stack labels");
+
+    let mut columns: Vec<Option<usize>> = vec![None; spans.len()];
+    let mut stacks: Stacks = vec![Vec::new(); spans.len()];
+    // Go bottom-up so each crate lands on the one below it.
+    for (row, line) in crate_rows.iter().enumerate().rev() {
+        let line_no = row + 1;
+        for (start, token) in tokens(line) {
+            let at = format!("line {line_no}, column {}", start + 1);
+            let &[b'[', c, b']'] = token.as_bytes() else {
+                bail!("{at}: expected a crate like \"[A]\", found {token:?}");
+            };
+            let mut under = spans
+                .iter()
+                .positions(|span| span.start < start + 3 && start < span.end);
+            let (Some(stack), None) = (under.next(), under.next()) else {
+                bail!("{at}: crate {token} is not under exactly one label");
+            };
+            let column = *columns[stack].get_or_insert(start + 1);
+            ensure!(
+                column == start + 1,
+                "{at}: crate {token} is not aligned with the crates of stack {}",
+                stack + 1
+            );
+            ensure!(
+                stacks[stack].len() == crate_rows.len() - 1 - row,
+                "{at}: crate {token} floats above stack {}",
+                stack + 1
+            );
+            stacks[stack].push(c);
+        }
+    }
+
+    let columns = columns
+        .iter()
+        .zip(&spans)
+        .map(|(column, span)| column.unwrap_or(span.start.max(1)));
+    let layout = Layout {
+        label_row: label_row.to_string(),
+        columns: columns.collect(),
+    };
+    Ok((stacks, layout))
+}
+
+/// The whitespace-separated tokens of a line with their starting byte offset.
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    let offset = |token: &str| token.as_ptr() as usize - line.as_ptr() as usize;
+    line.split_whitespace()
+        .map(move |token| (offset(token), token))
+}
+
+/// One line per changed stack, bottom to top before and after the move.
+fn render_diff(before: &Stacks, after: &Stacks) -> String {
+    let changed = before
+        .iter()
+        .zip(after)
+        .enumerate()
+        .filter(|(_, (b, a))| b != a);
+    let lines = changed.map(|(i, (b, a))| {
+        let [b, a] = [b, a].map(|s| String::from_utf8_lossy(s).into_owned());
+        format!("  {}: {b} -> {a}\n", i + 1)
+    });
+    lines.collect()
+}
+
+#[derive(Serialize)]
+struct TraceStep<'a> {
+    step: usize,
+    instruction: Option<&'a str>,
+    /// Each stack bottom to top.
+    stacks: Vec<String>,
+}
+
+#[derive(Copy, Clone)]
+enum TraceFormat {
+    Ascii,
+    Diff,
+    Json,
+}
+
+fn trace(
+    mut stacks: Stacks,
+    layout: &Layout,
+    moves: &[Move],
+    crane: Crane,
+    format: TraceFormat,
+) -> Result<()> {
+    let json_step = |step, instruction, stacks: &Stacks| {
+        let stacks = stacks
+            .iter()
+            .map(|s| String::from_utf8_lossy(s).into_owned());
+        let step = TraceStep {
+            step,
+            instruction,
+            stacks: stacks.collect(),
+        };
+        serde_json::to_string(&step)
+    };
+    match format {
+        TraceFormat::Ascii | TraceFormat::Diff => print!("{}", layout.render(&stacks)),
+        TraceFormat::Json => println!("{}", json_step(0, None, &stacks)?),
+    }
+    for (step, mv) in moves.iter().enumerate() {
+        let before = stacks.clone();
+        stacks.apply(mv, crane)?;
+        match format {
+            TraceFormat::Ascii => print!("\n{}\n{}", mv.line, layout.render(&stacks)),
+            TraceFormat::Diff => print!("{}\n{}", mv.line, render_diff(&before, &stacks)),
+            TraceFormat::Json => println!("{}", json_step(step + 1, Some(mv.line), &stacks)?),
+        }
+    }
+    Ok(())
+}
+
+/// Replays the moves on stack heights only, which are the same for both cranes, and returns
+/// the first instruction that moves more crates than its stack holds.
+fn first_impossible<'a, 'm>(stacks: &Stacks, moves: &'m [Move<'a>]) -> Option<&'m Move<'a>> {
+    let mut heights = stacks.iter().map(|s| s.len()).collect_vec();
+    moves.iter().find(|mv| {
+        let valid = mv.from != mv.to && mv.to < heights.len();
+        let fits = valid && heights.get(mv.from).is_some_and(|&h| h >= mv.count);
+        if fits {
+            heights[mv.from] -= mv.count;
+            heights[mv.to] += mv.count;
+        }
+        !fits
+    })
+}
+
+fn parse_input(input: &str) -> Result<(Stacks, Layout, Vec<Move<'_>>)> {
+    // The diagram ends at the first blank line, its leading whitespace is significant.
+    let lines = input.lines().collect_vec();
+    let diagram_len = lines
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .unwrap_or(lines.len());
+    let (stacks, layout) = parse_diagram(&lines[..diagram_len])?;
+    let instructions = lines
+        .iter()
+        .enumerate()
+        .skip(diagram_len)
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty());
+    let moves = parse_moves(&instructions.collect_vec())?;
+    Ok((stacks, layout, moves))
+}
+
+/// A random input with `crates` crates spread over `num_stacks` stacks, followed by
+/// `num_moves` possible moves of up to a whole stack, the worst case for `Vec` stacks.
+fn stress_input(num_stacks: usize, crates: usize, num_moves: usize) -> Result<String> {
+    ensure!(num_stacks >= 2, "need at least 2 stacks");
+    ensure!(crates > 0 || num_moves == 0, "need crates to move");
+    let mut rng = rand::thread_rng();
+    let mut stacks: Stacks = vec![Vec::new(); num_stacks];
+    for i in 0..crates {
+        stacks[i % num_stacks].push(rng.gen_range(b'A'..=b'Z'));
+    }
+    let layout = Layout {
+        label_row: (1..=num_stacks).map(|i| format!(" {i:<3}")).collect(),
+        columns: (0..num_stacks).map(|i| 4 * i + 1).collect(),
+    };
+    let mut out = layout.render(&stacks);
+    out.push('\n');
+
+    let mut heights = stacks.iter().map(|s| s.len()).collect_vec();
+    for _ in 0..num_moves {
+        let from = loop {
+            let from = rng.gen_range(0..num_stacks);
+            if heights[from] > 0 {
+                break from;
+            }
+        };
+        let to = (from + rng.gen_range(1..num_stacks)) % num_stacks;
+        let count = rng.gen_range(1..=heights[from]);
+        heights[from] -= count;
+        heights[to] += count;
+        writeln!(out, "move {count} from {} to {}", from + 1, to + 1)?;
+    }
+    Ok(out)
+}
+
+type StackHeads = fn(&Stacks, &[Move], Crane) -> Result<String>;
+
+fn bench(stacks: &Stacks, moves: &[Move]) -> Result<()> {
+    let stress = stress_input(3, 1_000_000, 100_000)?;
+    let (stress_stacks, _, stress_moves) = parse_input(&stress)?;
+    let inputs = [
+        ("input", stacks, moves),
+        ("random 10^6 crates", &stress_stacks, &stress_moves[..]),
+    ];
+    for (name, stacks, moves) in inputs {
+        println!("{name}, {} moves:", moves.len());
+        let backends: [(&str, StackHeads); 2] = [
+            ("vec", stack_heads::<Stacks>),
+            ("rope", stack_heads::<RopeStacks>),
+        ];
+        for (backend, f) in backends {
+            let start = std::time::Instant::now();
+            let part1 = f(stacks, moves, Crane::Mover9000)?;
+            let part2 = f(stacks, moves, Crane::Mover9001)?;
+            // Only print the ends of long answers.
+            let [part1, part2] = [part1, part2].map(|heads| match heads.len() {
+                0..=12 => heads,
+                len => format!("{}..{}", &heads[..4], &heads[len - 4..]),
+            });
+            println!(
+                "    {backend:<6} {part1:>12} {part2:>12} {:?}",
+                start.elapsed()
+            );
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day05.txt")?;
+    // `trace <ascii|diff|json> [1|2] [path]` prints the stacks after every move of part 1
+    // or 2, `check [path]` looks for impossible moves. `generate <stacks> <crates> <moves>`
+    // prints a random input that is slow for `Vec` stacks, `bench [path]` compares the
+    // stack backends on the input and on a generated one. Any other first argument is the
+    // input path.
+    let args = std::env::args().skip(1).collect_vec();
+    let mut mode = args.first().map(String::as_str);
+    if mode == Some("generate") {
+        let sizes: Vec<usize> = args[1..].iter().map(|a| a.parse()).try_collect()?;
+        let &[num_stacks, crates, num_moves] = &sizes[..] else {
+            bail!("usage: generate <stacks> <crates> <moves>");
+        };
+        print!("{}", stress_input(num_stacks, crates, num_moves)?);
+        return Ok(());
+    }
+    let path_arg = match mode {
+        Some("trace") => 3,
+        Some("check") | Some("bench") => 1,
+        _ => {
+            mode = None;
+            0
+        },
+    };
+    ensure!(args.len() <= path_arg + 1, "too many arguments");
+    let path = args
+        .get(path_arg)
+        .map_or("inputs/day05.txt", |p| p.as_str());
+    let input = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
     let start = std::time::Instant::now();
 
-    let nonempty = input.lines().map(str::trim).filter(|l| !l.is_empty());
-    let lines = nonempty.collect_vec();
-    let labels_line = lines.iter().position(|l| l.starts_with('1')).some()?;
-    let stacks = (0..(lines[labels_line].len() + 3) / 4).map(|s| {
-        let rows = lines[..labels_line].iter().rev();
-        let stack_items = rows.filter(|r| r.as_bytes().get(4 * s) == Some(&b'['));
-        stack_items.map(|r| r.as_bytes()[4 * s + 1]).collect()
-    });
+    let (stacks, layout, moves) = parse_input(&input)?;
+
+    match mode {
+        Some("trace") => {
+            let format = match args.get(1).map(String::as_str) {
+                Some("ascii") | None => TraceFormat::Ascii,
+                Some("diff") => TraceFormat::Diff,
+                Some("json") => TraceFormat::Json,
+                Some(other) => bail!("unknown trace format {other:?}"),
+            };
+            let crane = match args.get(2).map(String::as_str) {
+                Some("1") | None => Crane::Mover9000,
+                Some("2") => Crane::Mover9001,
+                Some(other) => bail!("unknown part {other:?}"),
+            };
+            return trace(stacks, &layout, &moves, crane, format);
+        },
+        Some("check") => {
+            match first_impossible(&stacks, &moves) {
+                Some(mv) => println!("impossible {mv}"),
+                None => println!("all {} moves are possible", moves.len()),
+            }
+            return Ok(());
+        },
+        Some("bench") => return bench(&stacks, &moves),
+        _ => {},
+    }
 
-    let part1 = stack_heads(stacks.clone().collect(), &lines[labels_line + 1..], true)?;
-    let part2 = stack_heads(stacks.collect(), &lines[labels_line + 1..], false)?;
+    let part1 = stack_heads::<Stacks>(&stacks, &moves, Crane::Mover9000)?;
+    let part2 = stack_heads::<Stacks>(&stacks, &moves, Crane::Mover9001)?;
 
     println!("part1: {part1}");
     println!("part2: {part2}");
     println!("time: {:?}", start.elapsed());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagram_lines(diagram: &str) -> Vec<&str> {
+        diagram.lines().collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let diagrams = [
+            "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n",
+            concat!(
+                "                                        [K]     \n",
+                "[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [L]     \n",
+                "[M] [N] [O] [P] [Q] [R] [S] [T] [U] [V] [W] [X] \n",
+                " 1   2   3   4   5   6   7   8   9   10  11  12 \n",
+            ),
+        ];
+        for diagram in diagrams {
+            let (stacks, layout) = parse_diagram(&diagram_lines(diagram)).unwrap();
+            assert_eq!(layout.render(&stacks), diagram);
+        }
+    }
+
+    #[test]
+    fn ragged_lines() {
+        let padded = parse_diagram(&["    [D]    ", "[N] [C]    ", "[Z] [M] [P]", " 1   2   3 "]);
+        let ragged = parse_diagram(&["    [D]", "[N] [C]", "[Z] [M] [P]", " 1   2   3"]);
+        assert_eq!(padded.unwrap().0, ragged.unwrap().0);
+        let (stacks, _) = parse_diagram(&["    [D]", " 1   2"]).unwrap();
+        assert_eq!(stacks, vec![vec![], vec![b'D']]);
+    }
+
+    #[test]
+    fn malformed_diagrams() {
+        let errors = [
+            (vec!["[A] [B]", " 1   3 "], "expected label 2"),
+            (vec!["[A] [BC]", " 1   2 "], "expected a crate"),
+            (vec!["[A]", "[B]"], "expected label 1"),
+            (
+                vec!["    [A]", "[B]    ", " 1   2 "],
+                "floats above stack 2",
+            ),
+            (
+                vec!["[A]      [B]", " 1   2 "],
+                "not under exactly one label",
+            ),
+            (vec![" [A]", "[B]", " 1"], "not aligned"),
+            (vec![], "missing stack diagram"),
+        ];
+        for (lines, message) in errors {
+            let error = parse_diagram(&lines).unwrap_err().to_string();
+            assert!(
+                error.contains(message),
+                "{error:?} should contain {message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_moves() {
+        let diagram = "[A]\n 1 \n\nmove 1 from 1 to 1\n\n";
+        let errors = [
+            ("mov 1 from 1 to 1", "line 6: expected a move"),
+            (
+                "move 99999999999999999999 from 1 to 1",
+                "line 6: invalid number \"99999999999999999999\"",
+            ),
+        ];
+        for (line, message) in errors {
+            let error = parse_input(&format!("{diagram}{line}\n")).err().unwrap();
+            let error = format!("{error:#}");
+            assert!(
+                error.starts_with(message),
+                "{error:?} should start with {message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn backends_agree() {
+        for num_stacks in 2..6 {
+            let input = stress_input(num_stacks, 2000, 2000).unwrap();
+            let (stacks, _, moves) = parse_input(&input).unwrap();
+            for crane in [Crane::Mover9000, Crane::Mover9001] {
+                let vec = stack_heads::<Stacks>(&stacks, &moves, crane).unwrap();
+                let rope = stack_heads::<RopeStacks>(&stacks, &moves, crane).unwrap();
+                assert_eq!(vec, rope);
+            }
+        }
+    }
+}
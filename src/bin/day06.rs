@@ -1,28 +1,51 @@
-use anyhow::{Context, Ok, Result};
-
-fn rolling_distinct_windows(s: &[u8], n: usize) -> impl Iterator<Item = (&[u8], usize)> {
-    let mut in_window = [0; 256];
-    let mut count = 0;
-    (0..s.len()).filter_map(move |i| {
-        if i >= n {
-            in_window[s[i - n] as usize] -= 1;
-            count -= (in_window[s[i - n] as usize] == 0) as usize;
-        }
-        in_window[s[i] as usize] += 1;
-        count += (in_window[s[i] as usize] == 1) as usize;
-        (i >= n - 1).then(|| (&s[i + 1 - n..i + 1], count))
-    })
-}
+use anyhow::{ensure, Context, Ok, Result};
+use aoc2022::distinct::{distinct_window_ends, first_distinct_windows, longest_distinct};
+use itertools::Itertools;
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day06.txt")?;
+    // `ends <w> [path]` prints the end of every window of w distinct bytes, `longest [path]`
+    // the longest run of distinct bytes. Any other first argument is the input path.
+    let args = std::env::args().skip(1).collect_vec();
+    let mut mode = args.first().map(String::as_str);
+    let path_arg = match mode {
+        Some("ends") => 2,
+        Some("longest") => 1,
+        _ => {
+            mode = None;
+            0
+        },
+    };
+    ensure!(args.len() <= path_arg + 1, "too many arguments");
+    let path = args
+        .get(path_arg)
+        .map_or("inputs/day06.txt", |p| p.as_str());
+    let input = std::fs::read(path).with_context(|| format!("reading {path}"))?;
     let start = std::time::Instant::now();
 
-    let bytes = input.trim().as_bytes();
-    let p1 = rolling_distinct_windows(bytes, 4).position(|(_, c)| c == 4);
-    let p2 = rolling_distinct_windows(bytes, 14).position(|(_, c)| c == 14);
-    println!("part1: {}", p1.context("marker not found")? + 4);
-    println!("part2: {}", p2.context("marker not found")? + 14);
+    // Any bytes can make up the stream, only a final newline is dropped.
+    let bytes = input.strip_suffix(b"\n").unwrap_or(&input);
+    match mode {
+        Some("ends") => {
+            let w = args.get(1).context("missing window size")?.parse()?;
+            ensure!(w > 0, "window size must be positive");
+            println!("{}", distinct_window_ends(bytes, w).join(" "));
+        },
+        Some("longest") => {
+            let run = longest_distinct(bytes);
+            let text = String::from_utf8_lossy(&bytes[run.clone()]);
+            println!(
+                "{} bytes at {}..{}: {text:?}",
+                run.len(),
+                run.start,
+                run.end
+            );
+        },
+        _ => {
+            let ends = first_distinct_windows(bytes, &[4, 14]);
+            println!("part1: {}", ends[0].context("marker not found")?);
+            println!("part2: {}", ends[1].context("marker not found")?);
+        },
+    }
     println!("time: {:?}", start.elapsed());
     Ok(())
 }
@@ -0,0 +1,173 @@
+use anyhow::{ensure, Context, Ok, Result};
+
+// Each lowercase letter is a bit of a u32. All variants return the number of characters
+// read up to the end of the first window of `w` distinct letters, for `w` in 1..=26.
+
+fn bit(c: u8) -> u32 {
+    1 << (c - b'a')
+}
+
+/// Toggles the bits of the letters entering and leaving the window. A letter's bit is set
+/// when it occurs an odd number of times, so the window is distinct when `w` bits are set.
+fn marker_rolling(s: &[u8], w: usize) -> Option<usize> {
+    let mut mask = 0u32;
+    for i in 0..s.len() {
+        mask ^= bit(s[i]);
+        if i >= w {
+            mask ^= bit(s[i - w]);
+        }
+        if mask.count_ones() as usize == w {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Scans each candidate window backwards. On a duplicate at `i`, no window starting at or
+/// before `i` can be distinct, so the next candidate starts at `i + 1`.
+fn marker_skip(s: &[u8], w: usize) -> Option<usize> {
+    let mut start = 0;
+    'windows: while start + w <= s.len() {
+        let mut seen = 0u32;
+        for i in (start..start + w).rev() {
+            if seen & bit(s[i]) != 0 {
+                start = i + 1;
+                continue 'windows;
+            }
+            seen |= bit(s[i]);
+        }
+        return Some(start + w);
+    }
+    None
+}
+
+const LANES: usize = 64;
+
+/// Checks `LANES` windows at a time with `w` XOR passes over fixed-size arrays, which the
+/// compiler turns into SIMD. Bits past the end are 0, so windows running off the end never
+/// have `w` bits set.
+fn marker_chunked(s: &[u8], w: usize) -> Option<usize> {
+    if w > 26 {
+        return None;
+    }
+    let mut bits = [0u32; LANES + 32];
+    for base in (0..s.len()).step_by(LANES) {
+        let chunk = &s[base..s.len().min(base + LANES + w)];
+        bits.fill(0);
+        for (b, &c) in bits.iter_mut().zip(chunk) {
+            *b = bit(c);
+        }
+
+        let mut masks = [0u32; LANES];
+        for j in 0..w {
+            for (mask, b) in masks.iter_mut().zip(&bits[j..j + LANES]) {
+                *mask ^= b;
+            }
+        }
+        let hits = masks.iter().enumerate().fold(0u64, |hits, (k, mask)| {
+            hits | ((mask.count_ones() as usize == w) as u64) << k
+        });
+        if hits != 0 {
+            return Some(base + hits.trailing_zeros() as usize + w);
+        }
+    }
+    None
+}
+
+type Marker = fn(&[u8], usize) -> Option<usize>;
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("inputs/day06.txt")?;
+    let start = std::time::Instant::now();
+
+    let bytes = input.trim().as_bytes();
+    ensure!(
+        bytes.iter().all(u8::is_ascii_lowercase),
+        "expected only lowercase letters"
+    );
+
+    // Run with `bench` as argument to compare the variants.
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        let variants: [(&str, Marker); 3] = [
+            ("rolling", marker_rolling),
+            ("skip", marker_skip),
+            ("chunked", marker_chunked),
+        ];
+        for (name, marker) in variants {
+            let start = std::time::Instant::now();
+            let p1 = marker(bytes, 4).context("marker not found")?;
+            let p2 = marker(bytes, 14).context("marker not found")?;
+            println!("{name:<8} {p1:>6} {p2:>6} {:?}", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    let p1 = marker_skip(bytes, 4);
+    let p2 = marker_skip(bytes, 14);
+    println!("part1: {}", p1.context("marker not found")?);
+    println!("part2: {}", p2.context("marker not found")?);
+    println!("time: {:?}", start.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    use super::*;
+
+    /// Counts every window from scratch.
+    fn marker_naive(s: &[u8], w: usize) -> Option<usize> {
+        let distinct = |window: &[u8]| window.iter().fold(0u32, |m, &c| m | bit(c)).count_ones();
+        let window = s
+            .windows(w)
+            .position(|window| distinct(window) as usize == w);
+        window.map(|start| start + w)
+    }
+
+    #[test]
+    fn variants_agree() {
+        let mut rng = rand::thread_rng();
+        let letters = (b'a'..=b'z').collect::<Vec<_>>();
+        for _ in 0..5000 {
+            let w = rng.gen_range(1..=26);
+            let alphabet = &letters[..rng.gen_range(1..=26)];
+            let len = rng.gen_range(0..300);
+            let mut s = (0..len)
+                .map(|_| *alphabet.choose(&mut rng).unwrap())
+                .collect::<Vec<_>>();
+            // Random strings rarely hold long distinct windows, so plant one half the time.
+            if len >= w && rng.gen() {
+                let at = rng.gen_range(0..=len - w);
+                let planted = letters.choose_multiple(&mut rng, w);
+                for (c, &p) in s[at..at + w].iter_mut().zip(planted) {
+                    *c = p;
+                }
+            }
+
+            let expected = marker_naive(&s, w);
+            assert_eq!(marker_rolling(&s, w), expected, "rolling, w = {w}");
+            assert_eq!(marker_skip(&s, w), expected, "skip, w = {w}");
+            assert_eq!(marker_chunked(&s, w), expected, "chunked, w = {w}");
+        }
+    }
+
+    #[test]
+    fn long_windows() {
+        let letters = (b'a'..=b'z').collect::<Vec<_>>();
+        let mut s = b"abcabc".repeat(50);
+        s.extend(&letters);
+        s.extend(b"zzz");
+        for w in 1..=26 {
+            let expected = marker_naive(&s, w);
+            assert!(expected.is_some());
+            assert_eq!(marker_rolling(&s, w), expected);
+            assert_eq!(marker_skip(&s, w), expected);
+            assert_eq!(marker_chunked(&s, w), expected);
+        }
+        for marker in [marker_rolling, marker_skip, marker_chunked] {
+            assert_eq!(marker(&s, 27), None);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 
 use anyhow::{Ok, Result};
+use aoc2022::num::lcm_all;
 use aoc2022::RegexExtract;
 use itertools::Itertools;
 use regex::Regex;
@@ -78,7 +79,7 @@ fn main() -> Result<()> {
         })
         .try_collect()?;
 
-    let rem = monkeys.iter().map(|m| m.divisor).product();
+    let rem = lcm_all(monkeys.iter().map(|m| m.divisor));
     let part1 = monkey_business(monkeys.clone(), 20, 3, 1u64 << 63);
     let part2 = monkey_business(monkeys, 10000, 1, rem);
 
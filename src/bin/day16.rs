@@ -2,6 +2,8 @@ use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
 use anyhow::{Ok, Result};
+use aoc2022::bitset::{BitSet, BitVec};
+use aoc2022::queue::{BucketQueue, PriorityQueue, RadixHeap};
 use aoc2022::{OptionSomeExt, Priority, RegexExtract};
 use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
@@ -12,28 +14,54 @@ struct Valve<'s> {
     neighbors: Vec<&'s str>,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct State {
+/// Opened valves, inline for small inputs and on the heap otherwise.
+trait ValveSet: Clone + Default + Ord + std::hash::Hash {
+    fn contains(&self, valve: usize) -> bool;
+    fn insert(&mut self, valve: usize);
+}
+
+impl<const WORDS: usize> ValveSet for BitSet<WORDS> {
+    fn contains(&self, valve: usize) -> bool {
+        BitSet::contains(self, valve)
+    }
+
+    fn insert(&mut self, valve: usize) {
+        BitSet::insert(self, valve);
+    }
+}
+
+impl ValveSet for BitVec {
+    fn contains(&self, valve: usize) -> bool {
+        BitVec::contains(self, valve)
+    }
+
+    fn insert(&mut self, valve: usize) {
+        BitVec::insert(self, valve);
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct State<S> {
     pressure: u32,
-    opened: u64,
+    opened: S,
     pos: [u16; 2],
     time: [u32; 2],
 }
 
-impl State {
+impl<S: ValveSet> State<S> {
     fn upper_bound(&self, best_valves: &[Vec<(usize, u32, u32)>]) -> u32 {
         let [mut max_t, mut min_t] = self.time;
-        let mut opened = self.opened;
+        let mut opened = self.opened.clone();
         let mut bound = self.pressure;
         'next_valve: loop {
             for (i, min_dist, f) in &best_valves[max_t as usize] {
-                if opened & (1 << i) == 0 {
+                if !opened.contains(*i) {
                     max_t -= min_dist;
                     bound += f * max_t as u32;
                     if max_t < min_t {
                         (min_t, max_t) = (max_t, min_t);
                     }
-                    opened |= 1 << i;
+                    opened.insert(*i);
                     continue 'next_valve;
                 }
             }
@@ -42,39 +70,48 @@ impl State {
     }
 }
 
-fn max_pressure_release(
+fn max_pressure_release<S: ValveSet, Q: PriorityQueue<Priority<u32, State<S>>> + Default>(
     start: u16,
     edges: &[Vec<(u16, u32)>],
     flows: &[u32],
     best_valves: &[Vec<(usize, u32, u32)>],
     time: [u32; 2],
 ) -> u32 {
+    let mut opened = S::default();
+    opened.insert(start as usize);
     let init = State {
         pressure: 0,
-        opened: 1 << start,
+        opened,
         pos: [start, start],
         time,
     };
 
     let mut seen = HashSet::with_capacity(1024);
     let mut best = 0;
-    let mut paths = BinaryHeap::with_capacity(1024);
-    paths.push(Priority(u32::MAX, init));
+    let mut paths = Q::default();
+    // A real bound rather than u32::MAX keeps the span of bucket queue priorities small.
+    paths.push(Priority(init.upper_bound(best_valves), init));
     while let Some(Priority(upper, cur)) = paths.pop() {
         if upper <= best {
             return best;
         }
 
-        if !seen.insert(State { pressure: 0, ..cur }) {
+        let key = State {
+            pressure: 0,
+            ..cur.clone()
+        };
+        if !seen.insert(key) {
             continue;
         }
 
         for (next, edge_len) in &edges[cur.pos[0] as usize] {
-            if cur.time[0] > *edge_len && cur.opened & (1 << next) == 0 {
+            if cur.time[0] > *edge_len && !cur.opened.contains(*next as usize) {
                 let new_time = cur.time[0] - edge_len;
+                let mut opened = cur.opened.clone();
+                opened.insert(*next as usize);
                 let mut next_state = State {
                     pressure: cur.pressure + flows[*next as usize] * new_time,
-                    opened: cur.opened | (1 << *next),
+                    opened,
                     pos: [*next, cur.pos[1]],
                     time: [new_time, cur.time[1]],
                 };
@@ -93,6 +130,36 @@ fn max_pressure_release(
     best
 }
 
+type Solver = fn(u16, &[Vec<(u16, u32)>], &[u32], &[Vec<(usize, u32, u32)>], [u32; 2]) -> u32;
+
+fn run<S: ValveSet>(
+    aa: u16,
+    edges: &[Vec<(u16, u32)>],
+    flows: &[u32],
+    best_valves: &[Vec<(usize, u32, u32)>],
+    bench: bool,
+) {
+    if bench {
+        let queues: [(&str, Solver); 3] = [
+            ("binary heap", max_pressure_release::<S, BinaryHeap<_>>),
+            ("bucket queue", max_pressure_release::<S, BucketQueue<_>>),
+            ("radix heap", max_pressure_release::<S, RadixHeap<_>>),
+        ];
+        for (name, solve) in queues {
+            let start = std::time::Instant::now();
+            let part1 = solve(aa, edges, flows, best_valves, [30, 0]);
+            let part2 = solve(aa, edges, flows, best_valves, [26, 26]);
+            println!("{name:<14} {part1:>6} {part2:>6} {:?}", start.elapsed());
+        }
+        return;
+    }
+
+    let part1 = max_pressure_release::<S, BinaryHeap<_>>(aa, edges, flows, best_valves, [30, 0]);
+    let part2 = max_pressure_release::<S, BinaryHeap<_>>(aa, edges, flows, best_valves, [26, 26]);
+    println!("part1: {part1}");
+    println!("part2: {part2}");
+}
+
 fn floyd_warshall(dists: &mut [u32], n: usize) {
     for k in 0..n {
         for i in 0..n {
@@ -114,7 +181,7 @@ fn main() -> Result<()> {
         r"Valve ([A-Z]{2}) has flow rate=(\d+); tunnels? leads? to valves? ([A-Z]{2}(?:, [A-Z]{2})*)",
     )?;
     for line in input.lines() {
-        let [name, flow, neighbors] = re.extract(line).some()?.1;
+        let [name, flow, neighbors] = re.extract(line).some_ctx("expected a valve line", line)?.1;
         ids.insert(name, valves.len());
         valves.push(Valve {
             flow: flow.parse()?,
@@ -122,7 +189,6 @@ fn main() -> Result<()> {
         });
     }
     let n = valves.len();
-    assert!(n <= 64);
 
     let mut dists = vec![u32::MAX; n * n];
     for (i, v) in valves.iter().enumerate() {
@@ -162,11 +228,15 @@ fn main() -> Result<()> {
                 .collect()
         })
         .collect();
-    let part1 = max_pressure_release(ids["AA"] as u16, &edges, &flows, &best_valves, [30, 0]);
-    let part2 = max_pressure_release(ids["AA"] as u16, &edges, &flows, &best_valves, [26, 26]);
-
-    println!("part1: {part1}");
-    println!("part2: {part2}");
-    println!("time: {:?}", start.elapsed());
+    let aa = ids["AA"] as u16;
+    let bench = std::env::args().nth(1).as_deref() == Some("bench");
+    match n {
+        0..=64 => run::<BitSet<1>>(aa, &edges, &flows, &best_valves, bench),
+        65..=128 => run::<BitSet<2>>(aa, &edges, &flows, &best_valves, bench),
+        _ => run::<BitVec>(aa, &edges, &flows, &best_valves, bench),
+    }
+    if !bench {
+        println!("time: {:?}", start.elapsed());
+    }
     Ok(())
 }
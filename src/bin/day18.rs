@@ -1,4 +1,5 @@
 use anyhow::{Ok, Result};
+use aoc2022::union_find::KeyedUnionFind;
 use aoc2022::OptionSomeExt;
 use hashbrown::{HashMap, HashSet};
 use itertools::{iproduct, Itertools};
@@ -8,27 +9,13 @@ fn offset(mut xyz: [i64; 3], dim: usize, offset: i64) -> [i64; 3] {
     xyz
 }
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("inputs/day18.txt")?;
-    let start = std::time::Instant::now();
-
-    let mut faces: HashMap<[i64; 3], usize> = HashMap::new();
-    let mut cubes: HashSet<[i64; 3]> = HashSet::new();
-    for line in input.lines() {
-        let xyz = line.split(',').map(|n| n.parse::<i64>());
-        let (x, y, z) = xyz.collect_tuple().some()?;
-        let xyz = [x? * 2, y? * 2, z? * 2];
-        cubes.insert(xyz);
-        for dim in 0..3 {
-            *faces.entry(offset(xyz, dim, -1)).or_default() += 1;
-            *faces.entry(offset(xyz, dim, 1)).or_default() += 1;
-        }
-    }
-
-    let part1 = faces.values().filter(|f| **f == 1).count();
+fn neighbors(xyz: [i64; 3]) -> impl Iterator<Item = [i64; 3]> {
+    iproduct!((0..3), [-2, 2]).map(move |(d, s)| offset(xyz, d, s))
+}
 
-    // Walk the air around the surface.
-    let mut part2 = 0;
+/// Walk the air around the surface.
+fn exterior_surface_walk(cubes: &HashSet<[i64; 3]>) -> Result<usize> {
+    let mut exterior = 0;
     let first_cube = *cubes.iter().min_by_key(|[x, _y, _z]| x).some()?;
     let mut to_visit = vec![(offset(first_cube, 0, -2), true)];
     let mut seen = HashSet::new();
@@ -37,7 +24,7 @@ fn main() -> Result<()> {
         if seen.insert(air) {
             let mut cubes_facing_air = 0;
             air_neighbors.clear();
-            for neighbor in iproduct!((0..3), [-2, 2]).map(|(d, s)| offset(air, d, s)) {
+            for neighbor in neighbors(air) {
                 if cubes.contains(&neighbor) {
                     cubes_facing_air += 1;
                 } else {
@@ -45,12 +32,84 @@ fn main() -> Result<()> {
                 }
             }
 
-            part2 += cubes_facing_air;
+            exterior += cubes_facing_air;
             if touches_surface || cubes_facing_air > 0 {
                 to_visit.extend(air_neighbors.iter().map(|n| (*n, cubes_facing_air > 0)));
             }
         }
     }
+    Ok(exterior)
+}
+
+/// Join all air cells of the bounding box, padded by one cell, into connected pockets and
+/// count the faces touching the pocket that contains the padding.
+fn exterior_surface_union_find(cubes: &HashSet<[i64; 3]>) -> Result<usize> {
+    let [lo, hi] = [i64::min, i64::max].map(|f| {
+        let bound = |d: usize| cubes.iter().map(|c| c[d]).reduce(f);
+        Some([bound(0)?, bound(1)?, bound(2)?])
+    });
+    let (lo, hi) = (lo.some()?.map(|c| c - 2), hi.some()?.map(|c| c + 2));
+    let in_box = |xyz: &[i64; 3]| (0..3).all(|d| lo[d] <= xyz[d] && xyz[d] <= hi[d]);
+
+    let mut air = KeyedUnionFind::new();
+    let [xs, ys, zs] = [0, 1, 2].map(|d| (lo[d]..=hi[d]).step_by(2));
+    for (x, y, z) in iproduct!(xs, ys, zs) {
+        if cubes.contains(&[x, y, z]) {
+            continue;
+        }
+        air.insert([x, y, z]);
+        // Only look back, the cells ahead are not inserted yet.
+        for back in (0..3).map(|d| offset([x, y, z], d, -2)) {
+            if in_box(&back) && !cubes.contains(&back) {
+                air.union([x, y, z], back);
+            }
+        }
+    }
+
+    let mut faces = 0;
+    for cube in cubes {
+        for neighbor in neighbors(*cube) {
+            faces += air.same(&neighbor, &lo) as usize;
+        }
+    }
+    Ok(faces)
+}
+
+type Exterior = fn(&HashSet<[i64; 3]>) -> Result<usize>;
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("inputs/day18.txt")?;
+    let start = std::time::Instant::now();
+
+    let mut faces: HashMap<[i64; 3], usize> = HashMap::new();
+    let mut cubes: HashSet<[i64; 3]> = HashSet::new();
+    for line in input.lines() {
+        let xyz = line.split(',').map(|n| n.parse::<i64>());
+        let (x, y, z) = xyz.collect_tuple().some()?;
+        let xyz = [x? * 2, y? * 2, z? * 2];
+        cubes.insert(xyz);
+        for dim in 0..3 {
+            *faces.entry(offset(xyz, dim, -1)).or_default() += 1;
+            *faces.entry(offset(xyz, dim, 1)).or_default() += 1;
+        }
+    }
+
+    let part1 = faces.values().filter(|f| **f == 1).count();
+
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        let methods: [(&str, Exterior); 2] = [
+            ("walk", exterior_surface_walk),
+            ("union find", exterior_surface_union_find),
+        ];
+        for (name, exterior_surface) in methods {
+            let start = std::time::Instant::now();
+            let part2 = exterior_surface(&cubes)?;
+            println!("{name:<14} {part2:>6} {:?}", start.elapsed());
+        }
+        return Ok(());
+    }
+
+    let part2 = exterior_surface_walk(&cubes)?;
 
     println!("part1: {}", part1);
     println!("part2: {}", part2);
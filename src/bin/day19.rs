@@ -2,6 +2,7 @@ use std::collections::BinaryHeap;
 use std::str::FromStr;
 
 use anyhow::{Ok, Result};
+use aoc2022::queue::{BucketQueue, PriorityQueue, RadixHeap};
 use aoc2022::{OptionSomeExt, Priority};
 use hashbrown::HashSet;
 use itertools::Itertools;
@@ -34,10 +35,14 @@ impl FromStr for Blueprint {
 }
 
 impl Blueprint {
-    pub fn best_num_geodes(&self, minutes: u32) -> u32 {
+    pub fn best_num_geodes<Q: PriorityQueue<Priority<u32, Execution>> + Default>(
+        &self,
+        minutes: u32,
+    ) -> u32 {
         let mut best = 0;
-        let mut executions = BinaryHeap::new();
-        executions.push(Priority(1, Execution::new()));
+        let mut executions = Q::default();
+        let init = Execution::new();
+        executions.push(Priority(init.geode_upper_bound(self, minutes), init));
         let mut seen = HashSet::new();
         while let Some(Priority(upper, ex)) = executions.pop() {
             if upper <= best {
@@ -151,15 +156,34 @@ impl Execution {
     }
 }
 
+fn solve<Q: PriorityQueue<Priority<u32, Execution>> + Default>(bps: &[Blueprint]) -> (u32, u32) {
+    let p1_best = bps.iter().map(|bp| bp.best_num_geodes::<Q>(24));
+    let part1: u32 = p1_best.enumerate().map(|(i, b)| b * (i as u32 + 1)).sum();
+    let p2_best = bps[..3].iter().map(|bp| bp.best_num_geodes::<Q>(32));
+    let part2: u32 = p2_best.product();
+    (part1, part2)
+}
+
+// Run with `bench` as argument to compare the priority queues.
+fn bench<Q: PriorityQueue<Priority<u32, Execution>> + Default>(name: &str, bps: &[Blueprint]) {
+    let start = std::time::Instant::now();
+    let (part1, part2) = solve::<Q>(bps);
+    println!("{name:<14} {part1:>6} {part2:>6} {:?}", start.elapsed());
+}
+
 fn main() -> Result<()> {
     let input = std::fs::read_to_string("inputs/day19.txt")?;
     let start = std::time::Instant::now();
 
     let bps: Vec<Blueprint> = input.lines().map(|l| l.parse()).try_collect()?;
-    let p1_best = bps.iter().map(|bp| bp.best_num_geodes(24));
-    let part1: u32 = p1_best.enumerate().map(|(i, b)| b * (i as u32 + 1)).sum();
-    let part2: u32 = bps[..3].iter().map(|bp| bp.best_num_geodes(32)).product();
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench::<BinaryHeap<_>>("binary heap", &bps);
+        bench::<BucketQueue<_>>("bucket queue", &bps);
+        bench::<RadixHeap<_>>("radix heap", &bps);
+        return Ok(());
+    }
 
+    let (part1, part2) = solve::<BinaryHeap<_>>(&bps);
     println!("part1: {part1}");
     println!("part2: {part2}");
     println!("time: {:?}", start.elapsed());
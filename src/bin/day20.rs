@@ -1,40 +1,72 @@
 use anyhow::{Ok, Result};
-use aoc2022::OptionSomeExt;
+use aoc2022::sequence::{BlockList, FenwickSlots, PositionalSequence};
 use aoc2022::treap::Treap;
+use aoc2022::OptionSomeExt;
 use itertools::Itertools;
+use rand::Rng;
 
-fn decrypt(nums: &[i64], mult: i64, k: usize) -> Result<i64> {
+fn decrypt<S>(nums: &[i64], mult: i64, k: usize) -> Result<i64>
+where
+    S: PositionalSequence<Item = i64> + Default,
+{
     let zero_idx = nums.iter().position(|x| *x == 0).some()?;
-    let mut rng = rand::thread_rng();
-    let mut trp = Treap::default();
-    let mut nodes = nums
+    let mut seq = S::default();
+    let nodes = nums
         .iter()
         .enumerate()
-        .map(|(i, n)| trp.insert(*n * mult, i, &mut rng))
+        .map(|(i, n)| seq.insert_at(*n * mult, i))
         .collect_vec();
 
     for _ in 0..k {
-        for node in &mut nodes {
-            let (value, rank) = trp.remove(*node).some()?;
+        for node in &nodes {
+            let value = *seq.get(*node).some()?;
+            let rank = seq.rank_of(*node).some()?;
             let new_rank = (value + rank as i64).rem_euclid(nums.len() as i64 - 1);
-            *node = trp.insert(value, new_rank as usize, &mut rng);
+            seq.move_to(*node, new_rank as usize);
         }
     }
 
-    let zero_rank = trp.rank(nodes[zero_idx]).some()?;
-    let grove = (1..=3).map(|k| {
-        let rank = trp.derank((zero_rank + 1000 * k) % nums.len());
-        trp.get(rank).some()
-    });
+    let zero_rank = seq.rank_of(nodes[zero_idx]).some()?;
+    let grove = (1..=3).map(|k| seq.get_at((zero_rank + 1000 * k) % nums.len()).some());
     Ok(itertools::process_results(grove, |it| it.sum())?)
 }
 
+type Decrypt = fn(&[i64], i64, usize) -> Result<i64>;
+
+// Run with `bench` as argument to compare the sequence backends.
+fn bench(nums: &[i64]) -> Result<()> {
+    let mut rng = rand::thread_rng();
+    let mut large = (0..1_000_000)
+        .map(|_| rng.gen_range(-10_000..10_000))
+        .collect_vec();
+    large[0] = 0;
+
+    for (name, nums, k) in [("input", nums, 10), ("random 10^6", &large[..], 1)] {
+        println!("{name}, n = {}, {k} round(s):", nums.len());
+        let backends: [(&str, Decrypt); 3] = [
+            ("treap", decrypt::<Treap<i64>>),
+            ("block list", decrypt::<BlockList<i64>>),
+            ("fenwick slots", decrypt::<FenwickSlots<i64>>),
+        ];
+        for (backend, f) in backends {
+            let start = std::time::Instant::now();
+            let answer = f(nums, 811589153, k)?;
+            println!("    {backend:<14} {answer:>20} {:?}", start.elapsed());
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let input = std::fs::read_to_string("inputs/day20.txt")?;
     let start = std::time::Instant::now();
     let nums: Vec<i64> = input.lines().map(|n| n.parse()).try_collect()?;
-    println!("part1: {}", decrypt(&nums, 1, 1)?);
-    println!("part2: {}", decrypt(&nums, 811589153, 10)?);
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        return bench(&nums);
+    }
+
+    println!("part1: {}", decrypt::<Treap<i64>>(&nums, 1, 1)?);
+    println!("part2: {}", decrypt::<Treap<i64>>(&nums, 811589153, 10)?);
     println!("time: {:?}", start.elapsed());
     Ok(())
 }
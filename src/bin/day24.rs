@@ -1,19 +1,21 @@
 use anyhow::{Ok, Result, Context};
-use aoc2022::Priority;
+use aoc2022::num::lcm;
+use aoc2022::queue::{BucketQueue, PriorityQueue, RadixHeap};
+use aoc2022::MinPriority;
 use hashbrown::HashSet;
 use itertools::Itertools;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
-fn gcd(a: usize, b: usize) -> usize {
-    if b == 0 {
-        a
-    } else {
-        gcd(b, a % b)
-    }
+// Lowest f-score first, preferring the deepest node on ties. The priority packs into 64 bits
+// for the bucket queue and radix heap.
+type Node = MinPriority<(u32, Reverse<u32>), [usize; 3]>;
+
+fn node(f: usize, pos: [usize; 3]) -> Node {
+    MinPriority::with_tiebreak(f as u32, Reverse(pos[2] as u32), pos)
 }
 
-fn astar(
+fn astar<Q: PriorityQueue<Node> + Default>(
     start: [usize; 3],
     stop: [usize; 2],
     dims: [usize; 3],
@@ -24,9 +26,9 @@ fn astar(
     let valid = |[x, y, t]: [usize; 3]| !forbidden[x + y * width + (t % period) * width * height];
     
     let mut reached = HashSet::new();
-    let mut heap = BinaryHeap::new();
-    heap.push(Priority(Reverse(h(start)), start));
-    while let Some(Priority(_, cur)) = heap.pop() {
+    let mut heap = Q::default();
+    heap.push(node(h(start), start));
+    while let Some(MinPriority(_, cur)) = heap.pop() {
         let [x, y, t] = cur;
         if [x, y] == stop {
             return Some(t);
@@ -37,7 +39,7 @@ fn astar(
             let ny = (y as i64 + dy).clamp(0, height as i64 - 1);
             let next = [nx as usize, ny as usize, t + 1];
             if valid(next) && reached.insert(next) {
-                heap.push(Priority(Reverse(h(next)), next));
+                heap.push(node(h(next), next));
             }
         }
     }
@@ -45,6 +47,25 @@ fn astar(
     None
 }
 
+fn trips<Q>(dims: [usize; 3], forbidden: &[bool]) -> Option<[usize; 2]>
+where
+    Q: PriorityQueue<Node> + Default,
+{
+    let [width, height, _] = dims;
+    let (entry, exit) = ([1, 0], [width - 2, height - 1]);
+    let there = astar::<Q>([entry[0], entry[1], 0], exit, dims, forbidden)?;
+    let back = astar::<Q>([exit[0], exit[1], there], entry, dims, forbidden)?;
+    let again = astar::<Q>([entry[0], entry[1], back], exit, dims, forbidden)?;
+    Some([there, again])
+}
+
+// Run with `bench` as argument to compare the priority queues.
+fn bench<Q: PriorityQueue<Node> + Default>(name: &str, dims: [usize; 3], forbidden: &[bool]) {
+    let start = std::time::Instant::now();
+    let [there, again] = trips::<Q>(dims, forbidden).unwrap_or_default();
+    println!("{name:<14} {there:>6} {again:>6} {:?}", start.elapsed());
+}
+
 fn main() -> Result<()> {
     let input = std::fs::read_to_string("inputs/day24.txt")?;
     let start = std::time::Instant::now();
@@ -53,7 +74,7 @@ fn main() -> Result<()> {
     let (width, height) = (lines[0].len(), lines.len());
 
     // Construct forbidden position mask.
-    let blizz_period = (width - 2) * (height - 2) / gcd(width - 2, height - 2);
+    let blizz_period = lcm(width - 2, height - 2);
     let mut forbidden = vec![false; width * height * blizz_period];
     let mut set = |x, y, t, v| forbidden[x + y * width + t * width * height] = v;
     for t in 0..blizz_period {
@@ -88,10 +109,15 @@ fn main() -> Result<()> {
     }
     
     let dims = [width, height, blizz_period];
-    let there = astar([1, 0, 0], [width - 2, height - 1], dims, &forbidden).context("no path")?;
-    let back = astar([width - 2, height - 1, there], [1, 0], dims, &forbidden).context("no path")?;
-    let again = astar([1, 0, back], [width - 2, height - 1], dims, &forbidden).context("no path")?;
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench::<BinaryHeap<_>>("binary heap", dims, &forbidden);
+        bench::<BucketQueue<_>>("bucket queue", dims, &forbidden);
+        bench::<RadixHeap<_>>("radix heap", dims, &forbidden);
+        return Ok(());
+    }
 
+    let paths = trips::<BinaryHeap<_>>(dims, &forbidden);
+    let [there, again] = paths.context("no path")?;
     println!("part1: {}", there);
     println!("part2: {}", again);
     println!("time: {:?}", start.elapsed());
@@ -0,0 +1,633 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
+};
+
+/// Iterator over the set bits of a word slice, in increasing order.
+#[derive(Clone)]
+pub struct Ones<'a> {
+    words: &'a [u64],
+    base: usize,
+    cur: u64,
+}
+
+impl<'a> Ones<'a> {
+    fn new(words: &'a [u64]) -> Self {
+        match words.split_first() {
+            Some((&cur, rest)) => Self {
+                words: rest,
+                base: 0,
+                cur,
+            },
+            None => Self {
+                words,
+                base: 0,
+                cur: 0,
+            },
+        }
+    }
+}
+
+impl Iterator for Ones<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.cur == 0 {
+            let (&next, rest) = self.words.split_first()?;
+            (self.cur, self.words, self.base) = (next, rest, self.base + 64);
+        }
+        let bit = self.cur.trailing_zeros() as usize;
+        self.cur &= self.cur - 1;
+        Some(self.base + bit)
+    }
+}
+
+/// Sets `sub` to the next smaller subset of `mask`, as `(sub - 1) & mask` on a multi-word
+/// integer. Returns false if `sub` was empty.
+fn prev_subset(sub: &mut [u64], mask: &[u64]) -> bool {
+    let Some(i) = sub.iter().position(|&w| w != 0) else {
+        return false;
+    };
+    sub[i] = (sub[i] - 1) & mask[i];
+    for (w, m) in sub[..i].iter_mut().zip(mask) {
+        *w = *m;
+    }
+    true
+}
+
+/// Compares word slices of the same length as little-endian integers.
+fn cmp_words(a: &[u64], b: &[u64]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+/// Set of integers below `64 * WORDS`, stored inline. Orders like the equivalent integer,
+/// so `BitSet<1>` orders like its `u64` mask.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BitSet<const WORDS: usize>([u64; WORDS]);
+
+impl<const WORDS: usize> Default for BitSet<WORDS> {
+    fn default() -> Self {
+        Self([0; WORDS])
+    }
+}
+
+impl<const WORDS: usize> BitSet<WORDS> {
+    pub const CAPACITY: usize = 64 * WORDS;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The set {0, 1, ..., n - 1}.
+    pub fn below(n: usize) -> Self {
+        assert!(n <= Self::CAPACITY, "{n} does not fit in a BitSet<{WORDS}>");
+        let mut words = [0; WORDS];
+        for (i, w) in words.iter_mut().enumerate() {
+            let bits = n.saturating_sub(64 * i).min(64);
+            *w = if bits == 64 {
+                u64::MAX
+            } else {
+                (1 << bits) - 1
+            };
+        }
+        Self(words)
+    }
+
+    pub fn from_words(words: [u64; WORDS]) -> Self {
+        Self(words)
+    }
+
+    pub fn words(&self) -> &[u64; WORDS] {
+        &self.0
+    }
+
+    /// Adds `i`, returning whether it was absent. Panics if `i >= CAPACITY`.
+    #[inline]
+    pub fn insert(&mut self, i: usize) -> bool {
+        let (w, bit) = (i / 64, 1 << (i % 64));
+        let absent = self.0[w] & bit == 0;
+        self.0[w] |= bit;
+        absent
+    }
+
+    /// Removes `i`, returning whether it was present.
+    #[inline]
+    pub fn remove(&mut self, i: usize) -> bool {
+        let Some(w) = self.0.get_mut(i / 64) else {
+            return false;
+        };
+        let bit = 1 << (i % 64);
+        let present = *w & bit != 0;
+        *w &= !bit;
+        present
+    }
+
+    #[inline]
+    pub fn contains(&self, i: usize) -> bool {
+        self.0.get(i / 64).is_some_and(|w| w & (1 << (i % 64)) != 0)
+    }
+
+    /// A copy of the set with `i` added.
+    #[inline]
+    pub fn with(mut self, i: usize) -> Self {
+        self.insert(i);
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = [0; WORDS];
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&w| w == 0)
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        let i = self.0.iter().rposition(|&w| w != 0)?;
+        Some(64 * i + 63 - self.0[i].leading_zeros() as usize)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a & !b == 0)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a & b == 0)
+    }
+
+    pub fn iter(&self) -> Ones<'_> {
+        Ones::new(&self.0)
+    }
+
+    /// All subsets of the set, from the set itself down to the empty set.
+    pub fn subsets(&self) -> impl Iterator<Item = Self> {
+        let mask = self.0;
+        let mut next = Some(*self);
+        std::iter::from_fn(move || {
+            let cur = next?;
+            let mut sub = cur.0;
+            next = prev_subset(&mut sub, &mask).then_some(Self(sub));
+            Some(cur)
+        })
+    }
+}
+
+impl<const WORDS: usize> Ord for BitSet<WORDS> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_words(&self.0, &other.0)
+    }
+}
+
+impl<const WORDS: usize> PartialOrd for BitSet<WORDS> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+macro_rules! bitset_op {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident, |$a:ident, $b:ident| $op:expr) => {
+        impl<const WORDS: usize> $assign_trait for BitSet<WORDS> {
+            #[inline]
+            fn $assign_method(&mut self, rhs: Self) {
+                for ($a, $b) in self.0.iter_mut().zip(rhs.0) {
+                    *$a = $op;
+                }
+            }
+        }
+
+        impl<const WORDS: usize> $trait for BitSet<WORDS> {
+            type Output = Self;
+
+            #[inline]
+            fn $method(mut self, rhs: Self) -> Self {
+                self.$assign_method(rhs);
+                self
+            }
+        }
+
+        impl $assign_trait<&BitVec> for BitVec {
+            fn $assign_method(&mut self, rhs: &BitVec) {
+                if self.words.len() < rhs.words.len() {
+                    self.words.resize(rhs.words.len(), 0);
+                }
+                let rhs_words = rhs.words.iter().copied().chain(std::iter::repeat(0));
+                for ($a, $b) in self.words.iter_mut().zip(rhs_words) {
+                    *$a = $op;
+                }
+                self.trim();
+            }
+        }
+
+        impl $trait for &BitVec {
+            type Output = BitVec;
+
+            fn $method(self, rhs: &BitVec) -> BitVec {
+                let mut out = self.clone();
+                out.$assign_method(rhs);
+                out
+            }
+        }
+    };
+}
+
+bitset_op!(BitOr, bitor, BitOrAssign, bitor_assign, |a, b| *a | b);
+bitset_op!(BitAnd, bitand, BitAndAssign, bitand_assign, |a, b| *a & b);
+bitset_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, |a, b| *a ^ b);
+bitset_op!(Sub, sub, SubAssign, sub_assign, |a, b| *a & !b);
+
+/// Complement within `CAPACITY`.
+impl<const WORDS: usize> Not for BitSet<WORDS> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0.map(|w| !w))
+    }
+}
+
+impl<const WORDS: usize> FromIterator<usize> for BitSet<WORDS> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<const WORDS: usize> Extend<usize> for BitSet<WORDS> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for i in iter {
+            self.insert(i);
+        }
+    }
+}
+
+impl<'a, const WORDS: usize> IntoIterator for &'a BitSet<WORDS> {
+    type Item = usize;
+    type IntoIter = Ones<'a>;
+
+    fn into_iter(self) -> Ones<'a> {
+        self.iter()
+    }
+}
+
+impl<const WORDS: usize> fmt::Debug for BitSet<WORDS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Growable counterpart of `BitSet` for universes whose size is only known at runtime.
+/// Trailing zero words are never stored, so equal sets compare and hash equal.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    words: Vec<u64>,
+}
+
+impl BitVec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The set {0, 1, ..., n - 1}.
+    pub fn below(n: usize) -> Self {
+        let mut words = vec![u64::MAX; n / 64];
+        let rem = n % 64;
+        if rem > 0 {
+            words.push((1 << rem) - 1);
+        }
+        Self { words }
+    }
+
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+
+    /// Adds `i`, returning whether it was absent.
+    pub fn insert(&mut self, i: usize) -> bool {
+        let (w, bit) = (i / 64, 1 << (i % 64));
+        if w >= self.words.len() {
+            self.words.resize(w + 1, 0);
+        }
+        let absent = self.words[w] & bit == 0;
+        self.words[w] |= bit;
+        absent
+    }
+
+    /// Removes `i`, returning whether it was present.
+    pub fn remove(&mut self, i: usize) -> bool {
+        let Some(w) = self.words.get_mut(i / 64) else {
+            return false;
+        };
+        let bit = 1 << (i % 64);
+        let present = *w & bit != 0;
+        *w &= !bit;
+        self.trim();
+        present
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words
+            .get(i / 64)
+            .is_some_and(|w| w & (1 << (i % 64)) != 0)
+    }
+
+    /// A copy of the set with `i` added.
+    pub fn with(&self, i: usize) -> Self {
+        let mut set = self.clone();
+        set.insert(i);
+        set
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        let i = self.words.len().checked_sub(1)?;
+        Some(64 * i + 63 - self.words[i].leading_zeros() as usize)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        let other_words = other.words.iter().chain(std::iter::repeat(&0));
+        self.words.iter().zip(other_words).all(|(a, b)| a & !b == 0)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.words.iter().zip(&other.words).all(|(a, b)| a & b == 0)
+    }
+
+    pub fn iter(&self) -> Ones<'_> {
+        Ones::new(&self.words)
+    }
+
+    /// All subsets of the set, from the set itself down to the empty set.
+    pub fn subsets(&self) -> impl Iterator<Item = Self> + '_ {
+        let mut next = Some(self.words.clone());
+        std::iter::from_fn(move || {
+            let cur = next.take()?;
+            let mut sub = cur.clone();
+            if prev_subset(&mut sub, &self.words) {
+                next = Some(sub);
+            }
+            let mut set = Self { words: cur };
+            set.trim();
+            Some(set)
+        })
+    }
+}
+
+impl Ord for BitVec {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let by_len = self.words.len().cmp(&other.words.len());
+        by_len.then_with(|| cmp_words(&self.words, &other.words))
+    }
+}
+
+impl PartialOrd for BitVec {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromIterator<usize> for BitVec {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<usize> for BitVec {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for i in iter {
+            self.insert(i);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a BitVec {
+    type Item = usize;
+    type IntoIter = Ones<'a>;
+
+    fn into_iter(self) -> Ones<'a> {
+        self.iter()
+    }
+}
+
+impl<const WORDS: usize> From<BitSet<WORDS>> for BitVec {
+    fn from(set: BitSet<WORDS>) -> Self {
+        let mut set = Self {
+            words: set.0.to_vec(),
+        };
+        set.trim();
+        set
+    }
+}
+
+impl fmt::Debug for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+    use std::collections::BTreeSet;
+    use std::hash::BuildHasher;
+
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// Every subset of `elements`, built bit by bit, largest first.
+    fn naive_subsets(elements: &[usize]) -> Vec<BitSet<2>> {
+        let mut subsets = (0..1u32 << elements.len())
+            .map(|mask| {
+                let chosen = elements
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask >> i & 1 == 1);
+                chosen.map(|(_, &e)| e).collect()
+            })
+            .collect::<Vec<BitSet<2>>>();
+        subsets.sort_unstable_by(|a, b| b.cmp(a));
+        subsets
+    }
+
+    #[test]
+    fn subsets_against_naive() {
+        let mut rng = StdRng::seed_from_u64(39);
+        // Elements around the word boundary exercise the borrow in `prev_subset`.
+        let candidates = [0, 1, 2, 5, 31, 62, 63, 64, 65, 100, 126, 127];
+        for _ in 0..200 {
+            let len = rng.gen_range(0..=8);
+            let elements = candidates
+                .choose_multiple(&mut rng, len)
+                .copied()
+                .collect::<Vec<_>>();
+            let set = elements.iter().copied().collect::<BitSet<2>>();
+            let expected = naive_subsets(&elements);
+            let subsets = set.subsets().collect::<Vec<_>>();
+            assert_eq!(subsets.len(), 1 << len);
+            assert_eq!(subsets, expected, "{set:?}");
+            assert!(subsets.iter().all(|s| s.is_subset(&set)));
+            assert_eq!(subsets.first(), Some(&set));
+            assert_eq!(subsets.last(), Some(&BitSet::new()));
+
+            let vec = BitVec::from(set);
+            let vec_subsets = vec.subsets().collect::<Vec<_>>();
+            assert!(vec_subsets.windows(2).all(|w| w[0] > w[1]));
+            let expected = expected.into_iter().map(BitVec::from).collect::<Vec<_>>();
+            assert_eq!(vec_subsets, expected, "{vec:?}");
+            assert_eq!(vec_subsets.last(), Some(&BitVec::new()));
+        }
+    }
+
+    #[test]
+    fn subsets_of_empty_and_full_words() {
+        assert!(BitSet::<2>::new().subsets().eq([BitSet::new()]));
+        assert!(BitVec::new().subsets().eq([BitVec::new()]));
+        let full = BitSet::<2>::below(128);
+        let mut subsets = full.subsets();
+        assert_eq!(subsets.next(), Some(full));
+        assert_eq!(subsets.next(), Some(full - BitSet::new().with(0)));
+        let bit_64 = BitVec::from(BitSet::<2>::new().with(64));
+        assert_eq!(bit_64.subsets().count(), 2);
+    }
+
+    /// The set as the integer with its bits set.
+    fn as_int(model: &BTreeSet<usize>) -> u128 {
+        model.iter().map(|&i| 1 << i).sum()
+    }
+
+    fn random_model(rng: &mut StdRng) -> BTreeSet<usize> {
+        // Elements cluster around the word boundary so both words are often in use.
+        let len = rng.gen_range(0..12);
+        (0..len)
+            .map(|_| rng.gen_range(50..80).min(rng.gen_range(0..128)))
+            .collect()
+    }
+
+    fn check_set(set: &BitSet<2>, model: &BTreeSet<usize>) {
+        assert!(set.iter().eq(model.iter().copied()), "{set:?} != {model:?}");
+        assert_eq!(set.len(), model.len());
+        assert_eq!(set.is_empty(), model.is_empty());
+        assert_eq!(set.first(), model.first().copied());
+        assert_eq!(set.last(), model.last().copied());
+        assert!((0..130).all(|i| set.contains(i) == model.contains(&i)));
+    }
+
+    fn check_vec(vec: &BitVec, model: &BTreeSet<usize>) {
+        assert_ne!(vec.words.last(), Some(&0), "untrimmed {:?}", vec.words);
+        assert!(vec.iter().eq(model.iter().copied()), "{vec:?} != {model:?}");
+        assert_eq!(vec.len(), model.len());
+        assert_eq!(vec.is_empty(), model.is_empty());
+        assert_eq!(vec.first(), model.first().copied());
+        assert_eq!(vec.last(), model.last().copied());
+        assert!((0..200).all(|i| vec.contains(i) == model.contains(&i)));
+    }
+
+    #[test]
+    fn operations_against_btreeset() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let hasher = RandomState::new();
+        for _ in 0..500 {
+            let (ma, mb) = (random_model(&mut rng), random_model(&mut rng));
+            let (a, b) = (
+                BitSet::<2>::from_iter(ma.iter().copied()),
+                BitSet::from_iter(mb.iter().copied()),
+            );
+            let (va, vb) = (BitVec::from(a), BitVec::from(b));
+            check_set(&a, &ma);
+            check_vec(&va, &ma);
+
+            let results = [
+                (a | b, &va | &vb, &ma | &mb),
+                (a & b, &va & &vb, &ma & &mb),
+                (a - b, &va - &vb, &ma - &mb),
+                (a ^ b, &va ^ &vb, &ma ^ &mb),
+            ];
+            for (set, vec, model) in &results {
+                check_set(set, model);
+                check_vec(vec, model);
+            }
+            let mut assigned = (a, va.clone());
+            assigned.0 -= b;
+            assigned.1 -= &vb;
+            assert_eq!(assigned, (results[2].0, results[2].1.clone()));
+            let complement = (0..128).filter(|i| !ma.contains(i)).collect();
+            check_set(&!a, &complement);
+
+            assert_eq!(a.is_subset(&b), ma.is_subset(&mb));
+            assert_eq!(va.is_subset(&vb), ma.is_subset(&mb));
+            assert_eq!(a.is_disjoint(&b), ma.is_disjoint(&mb));
+            assert_eq!(va.is_disjoint(&vb), ma.is_disjoint(&mb));
+            assert_eq!(a.cmp(&b), as_int(&ma).cmp(&as_int(&mb)));
+            assert_eq!(va.cmp(&vb), as_int(&ma).cmp(&as_int(&mb)));
+
+            // The same set built another way, through wider intermediate sets, must compare
+            // and hash equal.
+            let rebuilt = (a | b) - (b - a);
+            let rebuilt_vec = &(&va | &vb) - &(&vb - &va);
+            assert_eq!(rebuilt, a);
+            assert_eq!(rebuilt_vec, va);
+            assert_eq!(hasher.hash_one(rebuilt), hasher.hash_one(a));
+            assert_eq!(hasher.hash_one(&rebuilt_vec), hasher.hash_one(&va));
+            assert_eq!(rebuilt_vec.cmp(&va), Ordering::Equal);
+        }
+    }
+
+    #[test]
+    fn insert_and_remove_against_btreeset() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let (mut set, mut vec, mut model) = (BitSet::<2>::new(), BitVec::new(), BTreeSet::new());
+        for _ in 0..5000 {
+            let i = rng.gen_range(0..128);
+            if rng.gen_bool(0.5) {
+                let inserted = model.insert(i);
+                assert_eq!(set.insert(i), inserted);
+                assert_eq!(vec.insert(i), inserted);
+            } else {
+                let removed = model.remove(&i);
+                assert_eq!(set.remove(i), removed);
+                assert_eq!(vec.remove(i), removed);
+            }
+            check_set(&set, &model);
+            check_vec(&vec, &model);
+            assert_eq!(vec, BitVec::from(set));
+        }
+        assert!(!vec.remove(1000));
+        assert!(!set.remove(1000));
+        set.clear();
+        vec.clear();
+        check_set(&set, &BTreeSet::new());
+        check_vec(&vec, &BTreeSet::new());
+    }
+}
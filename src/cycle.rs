@@ -0,0 +1,297 @@
+use std::hash::Hash;
+
+use hashbrown::HashMap;
+
+/// Primitive integers with overflow-checked arithmetic, for extrapolated quantities.
+pub trait CheckedInt: Copy + TryFrom<u64> {
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_int {
+    ($($t:ty),*) => {
+        $(impl CheckedInt for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        })*
+    };
+}
+
+impl_checked_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// A cycle in a sequence of states s_0, s_1, ...: s_i = s_{i + len} for all i >= start.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Cycle {
+    /// The earliest step with the same state as `step`.
+    pub fn equivalent_step(&self, step: u64) -> usize {
+        let start = self.start as u64;
+        if step < start + self.len as u64 {
+            step as usize
+        } else {
+            (start + (step - start) % self.len as u64) as usize
+        }
+    }
+
+    /// Given the accumulated quantity after each step (`history[i]` after step i), extrapolates
+    /// it to `target` by assuming every pass through the cycle adds the same amount. `history`
+    /// must cover at least up to step `start + len`. Returns `None` if the result or any
+    /// intermediate value overflows `A`.
+    pub fn extrapolate<A: CheckedInt>(&self, history: &[A], target: u64) -> Option<A> {
+        let equivalent = self.equivalent_step(target);
+        if equivalent as u64 == target {
+            return history.get(equivalent).copied();
+        }
+        let cycles = A::try_from((target - self.start as u64) / self.len as u64).ok()?;
+        let per_cycle = history
+            .get(self.start + self.len)?
+            .checked_sub(history[self.start])?;
+        history[equivalent].checked_add(cycles.checked_mul(per_cycle)?)
+    }
+}
+
+/// Brent's cycle detection on the sequence start, step(start), step(step(start)), ...
+/// Only a couple of states are kept at any time, so this suits states that are cheap to
+/// compare but expensive to hash or store. Never returns if the sequence does not cycle.
+pub fn brent<S: Clone + PartialEq>(start: S, mut step: impl FnMut(&S) -> S) -> Cycle {
+    // Find the cycle length by teleporting the tortoise to the hare at powers of two.
+    let (mut power, mut len) = (1, 1);
+    let mut tortoise = start.clone();
+    let mut hare = step(&start);
+    while tortoise != hare {
+        if power == len {
+            tortoise = hare.clone();
+            (power, len) = (power * 2, 0);
+        }
+        hare = step(&hare);
+        len += 1;
+    }
+
+    // Then find the start with two pointers len steps apart.
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..len {
+        hare = step(&hare);
+    }
+    let mut cycle_start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        cycle_start += 1;
+    }
+
+    Cycle {
+        start: cycle_start,
+        len,
+    }
+}
+
+/// Floyd's tortoise and hare cycle detection, see `brent` which usually needs fewer steps.
+pub fn floyd<S: Clone + PartialEq>(start: S, mut step: impl FnMut(&S) -> S) -> Cycle {
+    let mut tortoise = step(&start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        let half = step(&hare);
+        hare = step(&half);
+    }
+
+    let mut cycle_start = 0;
+    tortoise = start;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        cycle_start += 1;
+    }
+
+    let mut len = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        len += 1;
+    }
+
+    Cycle {
+        start: cycle_start,
+        len,
+    }
+}
+
+/// Hash-based cycle detection to drive from inside a simulation loop. Each step, report a key
+/// identifying the state along with the accumulated quantity of interest, until a cycle is
+/// found:
+///
+/// ```
+/// use aoc2022::cycle::CycleDetector;
+///
+/// // The running total of x -> x^2 + 1 (mod 1000), starting from 3.
+/// let (mut x, mut total) = (3u64, 0u64);
+/// let mut detector = CycleDetector::new();
+/// let cycle = loop {
+///     total += x;
+///     if let Some(cycle) = detector.observe(x, total) {
+///         break cycle;
+///     }
+///     x = (x * x + 1) % 1000;
+/// };
+/// assert!(cycle.start + cycle.len < 1000);
+/// let total = detector.extrapolate(1_000_000_000_000).unwrap();
+/// ```
+pub struct CycleDetector<K, A> {
+    first_seen: HashMap<K, usize>,
+    history: Vec<A>,
+    cycle: Option<Cycle>,
+}
+
+impl<K, A> Default for CycleDetector<K, A> {
+    fn default() -> Self {
+        Self {
+            first_seen: HashMap::new(),
+            history: Vec::new(),
+            cycle: None,
+        }
+    }
+}
+
+impl<K: Hash + Eq, A> CycleDetector<K, A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the state at the next step, returning the cycle once a state repeats.
+    pub fn observe(&mut self, key: K, quantity: A) -> Option<Cycle> {
+        if self.cycle.is_none() {
+            let step = self.history.len();
+            self.history.push(quantity);
+            if let Some(first) = self.first_seen.insert(key, step) {
+                self.cycle = Some(Cycle {
+                    start: first,
+                    len: step - first,
+                });
+            }
+        }
+        self.cycle
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    pub fn history(&self) -> &[A] {
+        &self.history
+    }
+
+    /// The accumulated quantity at step `target`, if already observed or if a cycle is known.
+    pub fn extrapolate(&self, target: u64) -> Option<A>
+    where
+        A: CheckedInt,
+    {
+        match self.cycle {
+            Some(cycle) => cycle.extrapolate(&self.history, target),
+            None => self.history.get(usize::try_from(target).ok()?).copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// Remembers the first step of every state in a table.
+    fn naive_cycle(next: &[usize], start: usize) -> Cycle {
+        let mut first_seen = vec![None; next.len()];
+        let (mut state, mut step) = (start, 0);
+        loop {
+            if let Some(first) = first_seen[state] {
+                return Cycle {
+                    start: first,
+                    len: step - first,
+                };
+            }
+            first_seen[state] = Some(step);
+            state = next[state];
+            step += 1;
+        }
+    }
+
+    #[test]
+    fn detectors_against_naive() {
+        let mut rng = StdRng::seed_from_u64(37);
+        for _ in 0..500 {
+            // A random function on a finite set always ends up in a cycle.
+            let n = rng.gen_range(1..200);
+            let next = (0..n).map(|_| rng.gen_range(0..n)).collect::<Vec<_>>();
+            let weights = (0..n).map(|_| rng.gen_range(0..100u64)).collect::<Vec<_>>();
+            let start = rng.gen_range(0..n);
+            let expected = naive_cycle(&next, start);
+            assert_eq!(brent(start, |&s| next[s]), expected);
+            assert_eq!(floyd(start, |&s| next[s]), expected);
+
+            let mut detector = CycleDetector::new();
+            let (mut state, mut total) = (start, 0);
+            let cycle = loop {
+                total += weights[state];
+                if let Some(cycle) = detector.observe(state, total) {
+                    break cycle;
+                }
+                state = next[state];
+            };
+            assert_eq!(cycle, expected);
+            assert_eq!(detector.observe(next[state], 0), Some(cycle));
+            assert_eq!(detector.history().len(), cycle.start + cycle.len + 1);
+
+            let mut states = vec![start];
+            for _ in 0..3 * n {
+                states.push(next[*states.last().unwrap()]);
+            }
+            let totals = states.iter().scan(0, |total, &s| {
+                *total += weights[s];
+                Some(*total)
+            });
+            for (step, total) in totals.enumerate() {
+                assert_eq!(
+                    detector.extrapolate(step as u64),
+                    Some(total),
+                    "step {step}"
+                );
+                let equivalent = cycle.equivalent_step(step as u64);
+                assert!(equivalent <= step && equivalent < cycle.start + cycle.len);
+                assert_eq!(states[equivalent], states[step]);
+            }
+        }
+    }
+
+    #[test]
+    fn extrapolate_overflow() {
+        let cycle = Cycle { start: 1, len: 2 };
+        let history = [0u8, 1, 2, 3];
+        assert_eq!(cycle.extrapolate(&history, 2), Some(2));
+        assert_eq!(cycle.extrapolate(&history, 200), Some(200));
+        // 149 cycles fit in a u8, but the total doesn't.
+        assert_eq!(cycle.extrapolate(&history, 300), None);
+        assert_eq!(cycle.extrapolate(&history, 255), Some(255));
+        assert_eq!(cycle.extrapolate(&history, 256), None);
+        assert_eq!(cycle.extrapolate(&history, 1000), None);
+        // A decreasing quantity must not wrap around.
+        assert_eq!(cycle.extrapolate(&[9u8, 8, 7, 6], 5), None);
+        assert_eq!(cycle.extrapolate(&[9i8, 8, 7, 6], 5), Some(4));
+        assert_eq!(cycle.extrapolate(&history[..2], 10), None);
+    }
+}
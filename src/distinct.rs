@@ -0,0 +1,170 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+
+/// Iterator over the ends of `s`, yielding the longest substring ending there whose bytes are
+/// all distinct. Each step costs O(1) by remembering where every byte was last seen.
+pub struct DistinctRuns<'a> {
+    s: &'a [u8],
+    /// One past the last position of each byte, 0 if it wasn't seen yet.
+    last_seen: [usize; 256],
+    start: usize,
+    end: usize,
+}
+
+pub fn distinct_runs(s: &[u8]) -> DistinctRuns<'_> {
+    DistinctRuns {
+        s,
+        last_seen: [0; 256],
+        start: 0,
+        end: 0,
+    }
+}
+
+impl Iterator for DistinctRuns<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let &c = self.s.get(self.end)?;
+        self.start = self.start.max(self.last_seen[c as usize]);
+        self.end += 1;
+        self.last_seen[c as usize] = self.end;
+        Some(self.start..self.end)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.s.len() - self.end;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for DistinctRuns<'_> {}
+
+/// The end of every window of `w` distinct bytes, the window being `s[end - w..end]`.
+///
+/// # Panics
+///
+/// Panics if `w` is 0.
+pub fn distinct_window_ends(s: &[u8], w: usize) -> impl Iterator<Item = usize> + '_ {
+    assert!(w > 0, "window size must be positive");
+    distinct_runs(s)
+        .filter(move |run| run.len() >= w)
+        .map(|run| run.end)
+}
+
+/// The end of the first window of distinct bytes for each of `sizes`, found in a single pass
+/// that stops once every size is found.
+///
+/// # Panics
+///
+/// Panics if any size is 0.
+pub fn first_distinct_windows(s: &[u8], sizes: &[usize]) -> Vec<Option<usize>> {
+    assert!(!sizes.contains(&0), "window size must be positive");
+    let mut ends = vec![None; sizes.len()];
+    // A run that is long enough for a size also is for all smaller ones.
+    let mut pending = (0..sizes.len()).sorted_by_key(|&i| sizes[i]).peekable();
+    let mut runs = distinct_runs(s);
+    while let Some(&next) = pending.peek() {
+        let Some(run) = runs.next() else {
+            break;
+        };
+        if run.len() < sizes[next] {
+            continue;
+        }
+        for i in pending.peeking_take_while(|&i| sizes[i] <= run.len()) {
+            ends[i] = Some(run.end);
+        }
+    }
+    ends
+}
+
+/// The longest substring of `s` whose bytes are all distinct, the first one on ties.
+pub fn longest_distinct(s: &[u8]) -> Range<usize> {
+    let longest = |best: Range<usize>, run: Range<usize>| match run.len() > best.len() {
+        true => run,
+        false => best,
+    };
+    distinct_runs(s).fold(0..0, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn all_distinct(window: &[u8]) -> bool {
+        window.iter().all_unique()
+    }
+
+    fn random_bytes(rng: &mut StdRng) -> Vec<u8> {
+        // Few distinct bytes make repeats common, the high ones check the whole byte range.
+        let alphabet = rng.gen_range(1..12);
+        let len = rng.gen_range(0..60);
+        (0..len)
+            .map(|_| 250u8.wrapping_add(rng.gen_range(0..alphabet)))
+            .collect()
+    }
+
+    #[test]
+    fn against_naive() {
+        let mut rng = StdRng::seed_from_u64(50);
+        for _ in 0..1000 {
+            let s = random_bytes(&mut rng);
+            let runs = (1..=s.len()).map(|end| {
+                let start = (0..end).find(|&i| all_distinct(&s[i..end])).unwrap();
+                start..end
+            });
+            assert!(distinct_runs(&s).eq(runs.clone()), "{s:?}");
+            assert_eq!(distinct_runs(&s).len(), s.len());
+
+            let longest = runs.fold(0..0, |best, run| match run.len() > best.len() {
+                true => run,
+                false => best,
+            });
+            assert_eq!(longest_distinct(&s), longest, "{s:?}");
+
+            let naive_ends = |w: usize| {
+                let windows = s.windows(w).enumerate();
+                windows
+                    .filter(|(_, window)| all_distinct(window))
+                    .map(move |(i, _)| i + w)
+            };
+            for w in 1..14 {
+                assert!(
+                    distinct_window_ends(&s, w).eq(naive_ends(w)),
+                    "{s:?}, w = {w}"
+                );
+            }
+
+            // Unsorted and repeated sizes, some longer than the input.
+            let sizes = (0..rng.gen_range(0..6))
+                .map(|_| rng.gen_range(1..14))
+                .collect::<Vec<_>>();
+            let expected = sizes
+                .iter()
+                .map(|&w| naive_ends(w).next())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                first_distinct_windows(&s, &sizes),
+                expected,
+                "{s:?}, {sizes:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(distinct_runs(b"").next(), None);
+        assert_eq!(longest_distinct(b""), 0..0);
+        assert_eq!(first_distinct_windows(b"", &[1, 4]), [None, None]);
+        assert_eq!(first_distinct_windows(b"abc", &[]), []);
+    }
+
+    #[test]
+    #[should_panic(expected = "window size must be positive")]
+    fn zero_window_size() {
+        first_distinct_windows(b"abc", &[4, 0]);
+    }
+}
@@ -0,0 +1,411 @@
+use std::fmt;
+use std::ops::{Add, Range, RangeInclusive, Sub};
+
+/// Primitive integers usable as interval endpoints.
+pub trait PrimInt: Copy + Ord + fmt::Debug + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
+    const ONE: Self;
+}
+
+macro_rules! impl_prim_int {
+    ($($t:ty),*) => {
+        $(impl PrimInt for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+        })*
+    };
+}
+
+impl_prim_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Set of integers stored as sorted, disjoint and non-adjacent half-open intervals.
+/// Closed intervals are accepted by the `_inclusive` methods and converted on the way in,
+/// so they cannot end at `T::MAX`.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<(T, T)>,
+}
+
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self {
+            intervals: Vec::new(),
+        }
+    }
+
+    /// Number of disjoint intervals, not of covered integers (see `covered_len`).
+    pub fn num_intervals(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Number of integers in the set.
+    pub fn covered_len(&self) -> T {
+        self.intervals
+            .iter()
+            .fold(T::ZERO, |acc, &(lo, hi)| acc + (hi - lo))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.intervals.iter().map(|&(lo, hi)| lo..hi)
+    }
+
+    pub fn iter_inclusive(&self) -> impl Iterator<Item = RangeInclusive<T>> + '_ {
+        self.intervals.iter().map(|&(lo, hi)| lo..=hi - T::ONE)
+    }
+
+    /// The smallest and one past the largest element.
+    pub fn span(&self) -> Option<Range<T>> {
+        let first = self.intervals.first()?;
+        let last = self.intervals.last()?;
+        Some(first.0..last.1)
+    }
+
+    /// Index of the first interval ending at or after `x`.
+    fn first_ending_from(&self, x: T) -> usize {
+        self.intervals.partition_point(|&(_, hi)| hi < x)
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        let i = self.intervals.partition_point(|&(_, hi)| hi <= x);
+        self.intervals.get(i).is_some_and(|&(lo, _)| lo <= x)
+    }
+
+    /// Whether every integer of `range` is in the set, true for an empty range.
+    pub fn contains_range(&self, range: Range<T>) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+        let i = self.intervals.partition_point(|&(_, hi)| hi <= range.start);
+        self.intervals
+            .get(i)
+            .is_some_and(|&(lo, hi)| lo <= range.start && range.end <= hi)
+    }
+
+    /// Whether any integer of `range` is in the set.
+    pub fn overlaps(&self, range: Range<T>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+        let i = self.intervals.partition_point(|&(_, hi)| hi <= range.start);
+        self.intervals.get(i).is_some_and(|&(lo, _)| lo < range.end)
+    }
+
+    /// Adds `range`, merging it with the intervals it overlaps or touches.
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.is_empty() {
+            return;
+        }
+        let i = self.first_ending_from(range.start);
+        let j = self.intervals.partition_point(|&(lo, _)| lo <= range.end);
+        let mut merged = (range.start, range.end);
+        if i < j {
+            merged.0 = merged.0.min(self.intervals[i].0);
+            merged.1 = merged.1.max(self.intervals[j - 1].1);
+        }
+        self.intervals.splice(i..j, [merged]);
+    }
+
+    pub fn insert_inclusive(&mut self, range: RangeInclusive<T>) {
+        let (lo, hi) = range.into_inner();
+        if lo <= hi {
+            self.insert(lo..hi + T::ONE);
+        }
+    }
+
+    /// Removes every integer of `range`, splitting an interval if needed.
+    pub fn remove(&mut self, range: Range<T>) {
+        if range.is_empty() {
+            return;
+        }
+        let i = self.intervals.partition_point(|&(_, hi)| hi <= range.start);
+        let j = self.intervals.partition_point(|&(lo, _)| lo < range.end);
+        if i >= j {
+            return;
+        }
+        let (first_lo, last_hi) = (self.intervals[i].0, self.intervals[j - 1].1);
+        let left = (first_lo < range.start).then_some((first_lo, range.start));
+        let right = (range.end < last_hi).then_some((range.end, last_hi));
+        self.intervals.splice(i..j, left.into_iter().chain(right));
+    }
+
+    pub fn remove_inclusive(&mut self, range: RangeInclusive<T>) {
+        let (lo, hi) = range.into_inner();
+        if lo <= hi {
+            self.remove(lo..hi + T::ONE);
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut intervals: Vec<(T, T)> =
+            Vec::with_capacity(self.intervals.len() + other.intervals.len());
+        let (mut a, mut b) = (
+            self.intervals.iter().peekable(),
+            other.intervals.iter().peekable(),
+        );
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x.0 <= y.0 => a.next(),
+                (Some(_), Some(_)) => b.next(),
+                (Some(_), None) => a.next(),
+                (None, _) => b.next(),
+            };
+            let Some(&(lo, hi)) = next else { break };
+            match intervals.last_mut() {
+                Some(last) if lo <= last.1 => last.1 = last.1.max(hi),
+                _ => intervals.push((lo, hi)),
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while let (Some(&(a_lo, a_hi)), Some(&(b_lo, b_hi))) =
+            (self.intervals.get(i), other.intervals.get(j))
+        {
+            let (lo, hi) = (a_lo.max(b_lo), a_hi.min(b_hi));
+            if lo < hi {
+                intervals.push((lo, hi));
+            }
+            if a_hi < b_hi {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { intervals }
+    }
+
+    /// Elements of `self` that are not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let mut j = 0;
+        for &(mut lo, hi) in &self.intervals {
+            // Skip the intervals of `other` that end before this one starts.
+            while other.intervals.get(j).is_some_and(|&(_, b_hi)| b_hi <= lo) {
+                j += 1;
+            }
+            let mut k = j;
+            while let Some(&(b_lo, b_hi)) = other.intervals.get(k).filter(|&&(b_lo, _)| b_lo < hi) {
+                if lo < b_lo {
+                    intervals.push((lo, b_lo));
+                }
+                lo = lo.max(b_hi);
+                k += 1;
+            }
+            if lo < hi {
+                intervals.push((lo, hi));
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.iter().all(|r| other.contains_range(r))
+    }
+
+    /// The uncovered ranges strictly between consecutive intervals.
+    pub fn gaps(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.intervals.windows(2).map(|w| w[0].1..w[1].0)
+    }
+
+    /// The uncovered ranges within `bounds`.
+    pub fn gaps_within(&self, bounds: Range<T>) -> impl Iterator<Item = Range<T>> + '_ {
+        let i = self
+            .intervals
+            .partition_point(|&(_, hi)| hi <= bounds.start);
+        let mut pos = bounds.start;
+        let end = bounds.end;
+        self.intervals[i..]
+            .iter()
+            .map(Some)
+            .chain([None])
+            .map_while(move |interval| {
+                if pos >= end {
+                    return None;
+                }
+                let (lo, hi) = interval.copied().unwrap_or((end, end));
+                let gap = pos..lo.min(end);
+                pos = pos.max(hi);
+                Some(gap)
+            })
+            .filter(|gap| !gap.is_empty())
+    }
+}
+
+impl<T: PrimInt> From<Range<T>> for IntervalSet<T> {
+    fn from(range: Range<T>) -> Self {
+        let mut set = Self::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl<T: PrimInt> From<RangeInclusive<T>> for IntervalSet<T> {
+    fn from(range: RangeInclusive<T>) -> Self {
+        let mut set = Self::new();
+        set.insert_inclusive(range);
+        set
+    }
+}
+
+impl<T: PrimInt> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: PrimInt> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: PrimInt> Extend<Range<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = Range<T>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+impl<T: PrimInt> Extend<RangeInclusive<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = RangeInclusive<T>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert_inclusive(range);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.intervals.iter().map(|(lo, hi)| lo..hi))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn check_against_model(set: &IntervalSet<i32>, model: &BTreeSet<i32>) {
+        for w in set.intervals.windows(2) {
+            assert!(w[0].1 < w[1].0, "intervals overlap or touch: {set:?}");
+        }
+        assert!(set.intervals.iter().all(|(lo, hi)| lo < hi));
+        assert!(set.iter().flatten().eq(model.iter().copied()));
+        assert_eq!(set.covered_len(), model.len() as i32);
+        assert_eq!(set.is_empty(), model.is_empty());
+        let span = model.first().zip(model.last()).map(|(lo, hi)| *lo..hi + 1);
+        assert_eq!(set.span(), span);
+        assert!(set.iter_inclusive().flatten().eq(model.iter().copied()));
+    }
+
+    fn random_range(rng: &mut StdRng) -> Range<i32> {
+        let start = rng.gen_range(-5..70);
+        start..start + rng.gen_range(-2..12)
+    }
+
+    /// The maximal runs of integers in `bounds` not in `model`.
+    fn model_gaps(model: &BTreeSet<i32>, bounds: Range<i32>) -> Vec<Range<i32>> {
+        let missing = bounds.filter(|x| !model.contains(x)).collect::<Vec<_>>();
+        let mut gaps: Vec<Range<i32>> = Vec::new();
+        for x in missing {
+            match gaps.last_mut() {
+                Some(gap) if gap.end == x => gap.end += 1,
+                _ => gaps.push(x..x + 1),
+            }
+        }
+        gaps
+    }
+
+    #[test]
+    fn random_operations() {
+        let mut rng = StdRng::seed_from_u64(38);
+        for _ in 0..50 {
+            let mut set = IntervalSet::new();
+            let mut model = BTreeSet::new();
+            for _ in 0..200 {
+                let range = random_range(&mut rng);
+                match rng.gen_range(0..4) {
+                    0 => {
+                        set.insert(range.clone());
+                        model.extend(range);
+                    },
+                    1 => {
+                        set.insert_inclusive(range.start..=range.end);
+                        model.extend(range.start..=range.end);
+                    },
+                    2 => {
+                        set.remove(range.clone());
+                        model.retain(|x| !range.contains(x));
+                    },
+                    _ => {
+                        set.remove_inclusive(range.start..=range.end);
+                        model.retain(|x| !(range.start..=range.end).contains(x));
+                    },
+                }
+                check_against_model(&set, &model);
+
+                let range = random_range(&mut rng);
+                let x = range.start;
+                assert_eq!(set.contains(x), model.contains(&x));
+                let covered = range.clone().all(|x| model.contains(&x));
+                assert_eq!(set.contains_range(range.clone()), covered, "{range:?}");
+                let overlaps = range.clone().any(|x| model.contains(&x));
+                assert_eq!(set.overlaps(range.clone()), overlaps, "{range:?}");
+                let gaps = model_gaps(&model, range.clone());
+                assert!(set.gaps_within(range).eq(gaps));
+                if let Some(span) = set.span() {
+                    assert!(set.gaps().eq(model_gaps(&model, span)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut rng = StdRng::seed_from_u64(38);
+        for _ in 0..500 {
+            let n = rng.gen_range(0..6);
+            let (a, b): (IntervalSet<i32>, IntervalSet<i32>) = (
+                (0..n).map(|_| random_range(&mut rng)).collect(),
+                (0..n).map(|_| random_range(&mut rng)).collect(),
+            );
+            let model_a = a.iter().flatten().collect::<BTreeSet<_>>();
+            let model_b = b.iter().flatten().collect::<BTreeSet<_>>();
+            let union = a.union(&b);
+            check_against_model(&union, &(&model_a | &model_b));
+            check_against_model(&a.intersection(&b), &(&model_a & &model_b));
+            check_against_model(&a.difference(&b), &(&model_a - &model_b));
+            assert_eq!(a.is_subset(&b), model_a.is_subset(&model_b));
+            assert!(a.is_subset(&union) && b.is_subset(&union));
+        }
+    }
+
+    #[test]
+    fn inclusive_ranges_at_the_type_bounds() {
+        let mut set = IntervalSet::<u8>::from(0..=254);
+        assert_eq!(set.covered_len(), 255);
+        set.remove_inclusive(0..=0);
+        let (lo, hi) = (5, 4);
+        set.remove_inclusive(lo..=hi);
+        assert_eq!(set.iter_inclusive().collect::<Vec<_>>(), [1..=254]);
+        assert!(set.gaps_within(0..255).eq(Some(0..1)));
+    }
+}
@@ -1,10 +1,25 @@
 use core::panic::Location;
-use std::cmp::Ordering;
+use std::backtrace::Backtrace;
+use std::cmp::{Ordering, Reverse};
+use std::collections::VecDeque;
 use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::ops::Range;
 
+use hashbrown::{Equivalent, HashMap};
 use regex::{CaptureMatches, Captures, Regex};
+use slotmap::{Key, SecondaryMap, SlotMap};
 
+pub mod bitset;
+pub mod cycle;
+pub mod distinct;
+pub mod interval;
+pub mod num;
+pub mod queue;
+pub mod rope;
+pub mod sequence;
 pub mod treap;
+pub mod union_find;
 
 fn extract_from_capture<'t, const N: usize>(caps: Captures<'t>) -> (&'t str, [&'t str; N]) {
     let mut participating = caps.iter().flatten();
@@ -61,62 +76,381 @@ impl RegexExtract for Regex {
     }
 }
 
-pub trait GetDisjointMut {
-    type Item;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GetDisjointMutError<I> {
+    /// The index is out of bounds, or for maps, the key is not present.
+    OutOfBounds {
+        index: I,
+        len: usize,
+    },
+    Overlapping(I, I),
+}
 
-    fn get_disjoint_mut<const N: usize>(
+impl<I: fmt::Debug> std::error::Error for GetDisjointMutError<I> {}
+
+impl<I: fmt::Debug> fmt::Display for GetDisjointMutError<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { index, len } => {
+                write!(f, "index {index:?} out of bounds for length {len}")
+            },
+            Self::Overlapping(a, b) => write!(f, "indices {a:?} and {b:?} overlap"),
+        }
+    }
+}
+
+pub trait GetDisjointMut<I> {
+    type Item: ?Sized;
+
+    /// Returns mutable references to the items at all given indices at once. On failure the
+    /// error names the first index that is out of bounds or the first pair that overlaps.
+    fn try_get_disjoint_mut<const N: usize>(
         &mut self,
-        idxs: [usize; N],
-    ) -> Option<[&mut Self::Item; N]>;
+        idxs: [I; N],
+    ) -> Result<[&mut Self::Item; N], GetDisjointMutError<I>>;
+
+    /// Like `try_get_disjoint_mut`, without any checks.
+    ///
+    /// # Safety
+    ///
+    /// All indices must be in bounds and pairwise disjoint.
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        idxs: [I; N],
+    ) -> [&mut Self::Item; N];
+
+    /// Returns mutable references to the items at all given indices at once, or `None` if any
+    /// index is out of bounds or two indices overlap.
+    fn get_disjoint_mut<const N: usize>(&mut self, idxs: [I; N]) -> Option<[&mut Self::Item; N]> {
+        self.try_get_disjoint_mut(idxs).ok()
+    }
+}
+
+fn indices_disjoint_in_bounds<const N: usize>(idxs: &[usize; N], len: usize) -> bool {
+    let mut valid = true;
+    if N > 10 {
+        let mut sorted = *idxs;
+        sorted.sort_unstable();
+        valid &= sorted[N - 1] < len;
+        for i in 1..N {
+            valid &= sorted[i - 1] != sorted[i];
+        }
+    } else {
+        for i in 0..N {
+            valid &= idxs[i] < len;
+            for j in 0..i {
+                valid &= idxs[i] != idxs[j];
+            }
+        }
+    }
+    valid
+}
+
+fn ranges_overlap(r: &Range<usize>, q: &Range<usize>) -> bool {
+    !(r.is_empty() || q.is_empty() || r.end <= q.start || q.end <= r.start)
+}
+
+fn check_disjoint<I: Clone, const N: usize>(
+    idxs: &[I; N],
+    len: usize,
+    in_bounds: impl Fn(&I) -> bool,
+    overlaps: impl Fn(&I, &I) -> bool,
+) -> Result<(), GetDisjointMutError<I>> {
+    for (i, a) in idxs.iter().enumerate() {
+        if !in_bounds(a) {
+            let index = a.clone();
+            return Err(GetDisjointMutError::OutOfBounds { index, len });
+        }
+        if let Some(b) = idxs[..i].iter().find(|b| overlaps(a, b)) {
+            return Err(GetDisjointMutError::Overlapping(b.clone(), a.clone()));
+        }
+    }
+    Ok(())
 }
 
-impl<T> GetDisjointMut for [T] {
+// Looks up each key separately, detecting overlap by comparing the resulting pointers.
+fn disjoint_ptrs<I: Clone, V, const N: usize>(
+    keys: &[I; N],
+    len: usize,
+    mut get: impl FnMut(&I) -> Option<*mut V>,
+) -> Result<[*mut V; N], GetDisjointMutError<I>> {
+    let mut ptrs = [std::ptr::null_mut(); N];
+    for (i, k) in keys.iter().enumerate() {
+        let index = k.clone();
+        ptrs[i] = get(k).ok_or(GetDisjointMutError::OutOfBounds { index, len })?;
+        if let Some(j) = ptrs[..i].iter().position(|p| *p == ptrs[i]) {
+            return Err(GetDisjointMutError::Overlapping(keys[j].clone(), k.clone()));
+        }
+    }
+    Ok(ptrs)
+}
+
+impl<T> GetDisjointMut<usize> for [T] {
     type Item = T;
 
-    fn get_disjoint_mut<const N: usize>(
+    fn try_get_disjoint_mut<const N: usize>(
         &mut self,
         idxs: [usize; N],
-    ) -> Option<[&mut Self::Item; N]> {
-        // Check all in range and disjoint.
-        let mut valid = true;
-        if N > 10 {
-            let mut sorted = idxs;
-            sorted.sort_unstable();
-            valid &= idxs[N - 1] < self.len();
-            for i in 1..N {
-                valid &= idxs[i - 1] != idxs[i];
-            }
-        } else {
-            for i in 0..N {
-                valid &= idxs[i] < self.len();
-                for j in 0..i {
-                    valid &= idxs[i] != idxs[j];
-                }
-            }
-        }
+    ) -> Result<[&mut T; N], GetDisjointMutError<usize>> {
+        check_disjoint(&idxs, self.len(), |i| *i < self.len(), |a, b| a == b)?;
+        Ok(unsafe { self.get_disjoint_unchecked_mut(idxs) })
+    }
 
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        idxs: [usize; N],
+    ) -> [&mut T; N] {
         let p = self.as_mut_ptr();
-        valid.then(|| idxs.map(|i| unsafe { &mut *p.add(i) }))
+        idxs.map(|i| &mut *p.add(i))
+    }
+
+    fn get_disjoint_mut<const N: usize>(&mut self, idxs: [usize; N]) -> Option<[&mut T; N]> {
+        indices_disjoint_in_bounds(&idxs, self.len())
+            .then(|| unsafe { self.get_disjoint_unchecked_mut(idxs) })
     }
 }
 
+impl<T> GetDisjointMut<Range<usize>> for [T] {
+    type Item = [T];
+
+    fn try_get_disjoint_mut<const N: usize>(
+        &mut self,
+        ranges: [Range<usize>; N],
+    ) -> Result<[&mut [T]; N], GetDisjointMutError<Range<usize>>> {
+        let len = self.len();
+        let in_bounds = |r: &Range<usize>| r.start <= r.end && r.end <= len;
+        check_disjoint(&ranges, len, in_bounds, ranges_overlap)?;
+        Ok(unsafe { self.get_disjoint_unchecked_mut(ranges) })
+    }
+
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        ranges: [Range<usize>; N],
+    ) -> [&mut [T]; N] {
+        let p = self.as_mut_ptr();
+        ranges.map(|r| std::slice::from_raw_parts_mut(p.add(r.start), r.len()))
+    }
+}
+
+// Forwarding impl so that method resolution on a Vec finds this trait before the inherent
+// slice method of the same name.
+impl<T, I> GetDisjointMut<I> for Vec<T>
+where
+    [T]: GetDisjointMut<I>,
+{
+    type Item = <[T] as GetDisjointMut<I>>::Item;
+
+    fn try_get_disjoint_mut<const N: usize>(
+        &mut self,
+        idxs: [I; N],
+    ) -> Result<[&mut Self::Item; N], GetDisjointMutError<I>> {
+        <[T] as GetDisjointMut<I>>::try_get_disjoint_mut(self, idxs)
+    }
+
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        idxs: [I; N],
+    ) -> [&mut Self::Item; N] {
+        <[T] as GetDisjointMut<I>>::get_disjoint_unchecked_mut(self, idxs)
+    }
+
+    fn get_disjoint_mut<const N: usize>(&mut self, idxs: [I; N]) -> Option<[&mut Self::Item; N]> {
+        <[T] as GetDisjointMut<I>>::get_disjoint_mut(self, idxs)
+    }
+}
+
+impl<T> GetDisjointMut<usize> for VecDeque<T> {
+    type Item = T;
+
+    fn try_get_disjoint_mut<const N: usize>(
+        &mut self,
+        idxs: [usize; N],
+    ) -> Result<[&mut T; N], GetDisjointMutError<usize>> {
+        check_disjoint(&idxs, self.len(), |i| *i < self.len(), |a, b| a == b)?;
+        Ok(unsafe { self.get_disjoint_unchecked_mut(idxs) })
+    }
+
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        idxs: [usize; N],
+    ) -> [&mut T; N] {
+        let ptrs = idxs.map(|i| self.get_mut(i).unwrap_unchecked() as *mut T);
+        ptrs.map(|p| &mut *p)
+    }
+
+    fn get_disjoint_mut<const N: usize>(&mut self, idxs: [usize; N]) -> Option<[&mut T; N]> {
+        indices_disjoint_in_bounds(&idxs, self.len())
+            .then(|| unsafe { self.get_disjoint_unchecked_mut(idxs) })
+    }
+}
+
+impl<K: Key, V> GetDisjointMut<K> for SlotMap<K, V> {
+    type Item = V;
+
+    fn try_get_disjoint_mut<const N: usize>(
+        &mut self,
+        keys: [K; N],
+    ) -> Result<[&mut V; N], GetDisjointMutError<K>> {
+        check_disjoint(&keys, self.len(), |k| self.contains_key(*k), |a, b| a == b)?;
+        Ok(unsafe { SlotMap::get_disjoint_unchecked_mut(self, keys) })
+    }
+
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(&mut self, keys: [K; N]) -> [&mut V; N] {
+        SlotMap::get_disjoint_unchecked_mut(self, keys)
+    }
+
+    fn get_disjoint_mut<const N: usize>(&mut self, keys: [K; N]) -> Option<[&mut V; N]> {
+        SlotMap::get_disjoint_mut(self, keys)
+    }
+}
+
+impl<K: Key, V> GetDisjointMut<K> for SecondaryMap<K, V> {
+    type Item = V;
+
+    fn try_get_disjoint_mut<const N: usize>(
+        &mut self,
+        keys: [K; N],
+    ) -> Result<[&mut V; N], GetDisjointMutError<K>> {
+        check_disjoint(&keys, self.len(), |k| self.contains_key(*k), |a, b| a == b)?;
+        Ok(unsafe { SecondaryMap::get_disjoint_unchecked_mut(self, keys) })
+    }
+
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(&mut self, keys: [K; N]) -> [&mut V; N] {
+        SecondaryMap::get_disjoint_unchecked_mut(self, keys)
+    }
+
+    fn get_disjoint_mut<const N: usize>(&mut self, keys: [K; N]) -> Option<[&mut V; N]> {
+        SecondaryMap::get_disjoint_mut(self, keys)
+    }
+}
+
+impl<'q, K, V, S, Q> GetDisjointMut<&'q Q> for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    Q: Hash + Equivalent<K> + ?Sized,
+    S: BuildHasher,
+{
+    type Item = V;
+
+    fn try_get_disjoint_mut<const N: usize>(
+        &mut self,
+        keys: [&'q Q; N],
+    ) -> Result<[&mut V; N], GetDisjointMutError<&'q Q>> {
+        let len = self.len();
+        let ptrs = disjoint_ptrs(&keys, len, |k| self.get_mut(*k).map(|v| v as *mut V))?;
+        Ok(ptrs.map(|p| unsafe { &mut *p }))
+    }
+
+    unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        keys: [&'q Q; N],
+    ) -> [&mut V; N] {
+        self.get_many_unchecked_mut(keys).unwrap_unchecked()
+    }
+
+    fn get_disjoint_mut<const N: usize>(&mut self, keys: [&'q Q; N]) -> Option<[&mut V; N]> {
+        self.get_many_mut(keys)
+    }
+}
+
+const SNIPPET_LEN: usize = 80;
+
+#[derive(Debug)]
+struct NoneErrorDetails {
+    message: Option<String>,
+    value: Option<String>,
+    backtrace: Backtrace,
+}
+
+/// Error returned by the `OptionSomeExt` methods. All details are only gathered once a `None`
+/// is actually encountered. A backtrace is captured if enabled through `RUST_BACKTRACE` or
+/// `RUST_LIB_BACKTRACE`.
 #[derive(Debug)]
 pub struct NoneError {
     location: &'static Location<'static>,
+    details: Box<NoneErrorDetails>,
+}
+
+impl NoneError {
+    #[cold]
+    #[inline(never)]
+    fn new(
+        location: &'static Location<'static>,
+        message: Option<String>,
+        value: Option<String>,
+    ) -> Self {
+        let details = NoneErrorDetails {
+            message,
+            value,
+            backtrace: Backtrace::capture(),
+        };
+        Self {
+            location,
+            details: Box::new(details),
+        }
+    }
+
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.details.message.as_deref()
+    }
+
+    /// The (possibly truncated) debug representation of the offending value.
+    pub fn value(&self) -> Option<&str> {
+        self.details.value.as_deref()
+    }
+
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.details.backtrace
+    }
 }
 
 impl std::error::Error for NoneError {}
 
 impl fmt::Display for NoneError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "some() was called on None value at {}", self.location)
+        match &self.details.message {
+            Some(msg) => write!(f, "{msg}")?,
+            None => write!(f, "some() was called on None value")?,
+        }
+        if let Some(value) = &self.details.value {
+            write!(f, " (value: {value})")?;
+        }
+        write!(f, " at {}", self.location)
+    }
+}
+
+fn snippet<V: fmt::Debug + ?Sized>(value: &V) -> String {
+    let mut s = format!("{value:?}");
+    if s.chars().count() > SNIPPET_LEN {
+        let cut = s
+            .char_indices()
+            .nth(SNIPPET_LEN)
+            .map(|(i, _)| i)
+            .unwrap_or(s.len());
+        s.truncate(cut);
+        s.push_str("...");
     }
+    s
 }
 
 pub trait OptionSomeExt {
     type Item;
 
     fn some(self) -> Result<Self::Item, NoneError>;
+
+    /// Like `some`, with a lazily built message describing what was expected.
+    fn some_with<M: Into<String>, F: FnOnce() -> M>(self, f: F) -> Result<Self::Item, NoneError>;
+
+    /// Like `some`, with a message and a snippet of the value that was being processed.
+    fn some_ctx<V: fmt::Debug + ?Sized>(
+        self,
+        msg: &str,
+        value: &V,
+    ) -> Result<Self::Item, NoneError>;
 }
 
 impl<T> OptionSomeExt for Option<T> {
@@ -126,16 +460,51 @@ impl<T> OptionSomeExt for Option<T> {
     fn some(self) -> Result<Self::Item, NoneError> {
         match self {
             Some(val) => Ok(val),
-            None => Err(NoneError {
-                location: Location::caller(),
-            }),
+            None => Err(NoneError::new(Location::caller(), None, None)),
+        }
+    }
+
+    #[track_caller]
+    fn some_with<M: Into<String>, F: FnOnce() -> M>(self, f: F) -> Result<Self::Item, NoneError> {
+        match self {
+            Some(val) => Ok(val),
+            None => Err(NoneError::new(Location::caller(), Some(f().into()), None)),
+        }
+    }
+
+    #[track_caller]
+    fn some_ctx<V: fmt::Debug + ?Sized>(
+        self,
+        msg: &str,
+        value: &V,
+    ) -> Result<Self::Item, NoneError> {
+        match self {
+            Some(val) => Ok(val),
+            None => {
+                let (msg, value) = (Some(msg.to_owned()), Some(snippet(value)));
+                Err(NoneError::new(Location::caller(), msg, value))
+            },
         }
     }
 }
 
+/// Orders by priority only, highest first in a max-heap such as `BinaryHeap`. Ties pop in
+/// arbitrary order unless broken explicitly, see `with_tiebreak` and `fifo`.
 #[derive(Copy, Clone, Debug)]
 pub struct Priority<P, T>(pub P, pub T);
 
+impl<P, T> Priority<P, T> {
+    /// Among equal priorities, the highest tie-break key pops first.
+    pub fn with_tiebreak<K>(priority: P, tiebreak: K, value: T) -> Priority<(P, K), T> {
+        Priority((priority, tiebreak), value)
+    }
+
+    /// Among equal priorities, the earliest pushed pops first.
+    pub fn fifo(priority: P, seq: &mut Fifo, value: T) -> Priority<(P, Reverse<u64>), T> {
+        Priority((priority, Reverse(seq.bump())), value)
+    }
+}
+
 impl<P: Ord + Eq, T> Ord for Priority<P, T> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.0.cmp(&other.0)
@@ -155,3 +524,386 @@ impl<P: Eq, T> PartialEq for Priority<P, T> {
 }
 
 impl<P: Eq, T> Eq for Priority<P, T> {}
+
+/// Like `Priority` but with reversed order, so that the lowest priority pops first from a
+/// max-heap such as `BinaryHeap`.
+#[derive(Copy, Clone, Debug)]
+pub struct MinPriority<P, T>(pub P, pub T);
+
+impl<P, T> MinPriority<P, T> {
+    /// Among equal priorities, the lowest tie-break key pops first.
+    pub fn with_tiebreak<K>(priority: P, tiebreak: K, value: T) -> MinPriority<(P, K), T> {
+        MinPriority((priority, tiebreak), value)
+    }
+
+    /// Among equal priorities, the earliest pushed pops first.
+    pub fn fifo(priority: P, seq: &mut Fifo, value: T) -> MinPriority<(P, u64), T> {
+        MinPriority((priority, seq.bump()), value)
+    }
+}
+
+impl<P: Ord + Eq, T> Ord for MinPriority<P, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl<P: Ord + Eq, T> PartialOrd for MinPriority<P, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: Eq, T> PartialEq for MinPriority<P, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<P: Eq, T> Eq for MinPriority<P, T> {}
+
+/// Insertion counter for first-in first-out tie-breaking.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Fifo(u64);
+
+impl Fifo {
+    pub fn bump(&mut self) -> u64 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+/// An `f64` with a total order (as per `f64::total_cmp`) for use as priority.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TotalF64(pub f64);
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TotalF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalF64 {}
+
+impl Hash for TotalF64 {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+    use std::collections::BinaryHeap;
+
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn disjoint_slice_indices() {
+        let mut v = vec![0, 1, 2, 3, 4];
+        let [a, b] = v.try_get_disjoint_mut([3, 1]).unwrap();
+        (*a, *b) = (*b, *a);
+        assert_eq!(v, [0, 3, 2, 1, 4]);
+
+        assert_eq!(
+            v.try_get_disjoint_mut([0, 2, 0]).unwrap_err(),
+            GetDisjointMutError::Overlapping(0, 0)
+        );
+        assert_eq!(
+            v.try_get_disjoint_mut([1, 5]).unwrap_err(),
+            GetDisjointMutError::OutOfBounds { index: 5, len: 5 }
+        );
+        assert!(v.get_disjoint_mut([4, 4]).is_none());
+        assert!(v.get_disjoint_mut([0, 9]).is_none());
+        let no_indices: [usize; 0] = [];
+        assert!(v.try_get_disjoint_mut(no_indices).is_ok());
+    }
+
+    #[test]
+    fn disjoint_many_indices() {
+        // More than 10 indices go through the sorted check.
+        let mut v = (0..20).collect_vec();
+        let idxs = [19, 0, 7, 3, 12, 5, 18, 1, 9, 14, 2, 11];
+        for r in v.get_disjoint_mut(idxs).unwrap() {
+            *r += 100;
+        }
+        for (i, x) in v.iter().enumerate() {
+            assert_eq!(*x, i + if idxs.contains(&i) { 100 } else { 0 });
+        }
+
+        let mut duplicate = idxs;
+        duplicate[11] = 7;
+        assert!(v.get_disjoint_mut(duplicate).is_none());
+        let mut out_of_bounds = idxs;
+        out_of_bounds[0] = 20;
+        assert!(v.get_disjoint_mut(out_of_bounds).is_none());
+        assert_eq!(
+            v.try_get_disjoint_mut(duplicate).unwrap_err(),
+            GetDisjointMutError::Overlapping(7, 7)
+        );
+    }
+
+    #[test]
+    fn disjoint_ranges() {
+        let mut v = (0..10).collect_vec();
+        let [a, b, empty] = v.try_get_disjoint_mut([5..8, 0..2, 6..6]).unwrap();
+        a[0] = 50;
+        b[1] = 10;
+        assert!(empty.is_empty());
+        assert_eq!(v, [0, 10, 2, 3, 4, 50, 6, 7, 8, 9]);
+
+        // Empty ranges overlap nothing, even inside another range.
+        assert!(v.get_disjoint_mut([0..10, 3..3, 10..10]).is_some());
+        assert_eq!(
+            v.try_get_disjoint_mut([0..3, 4..6, 2..5]).unwrap_err(),
+            GetDisjointMutError::Overlapping(0..3, 2..5)
+        );
+        assert_eq!(
+            v.try_get_disjoint_mut([0..3, 8..11]).unwrap_err(),
+            GetDisjointMutError::OutOfBounds {
+                index: 8..11,
+                len: 10
+            }
+        );
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = [0..1, 4..2];
+        assert!(v.get_disjoint_mut(reversed).is_none());
+    }
+
+    #[test]
+    fn disjoint_vec_deque() {
+        // Front pushes make the deque wrap around its buffer.
+        let mut d = VecDeque::with_capacity(8);
+        d.extend([3, 4, 5, 6]);
+        for x in [2, 1, 0] {
+            d.push_front(x);
+        }
+        let [a, b, c] = d.try_get_disjoint_mut([0, 6, 3]).unwrap();
+        (*a, *b, *c) = (*a + 10, *b + 10, *c + 10);
+        assert!(d.iter().eq(&[10, 1, 2, 13, 4, 5, 16]));
+        assert_eq!(
+            d.try_get_disjoint_mut([1, 1]).unwrap_err(),
+            GetDisjointMutError::Overlapping(1, 1)
+        );
+        assert_eq!(
+            d.try_get_disjoint_mut([7]).unwrap_err(),
+            GetDisjointMutError::OutOfBounds { index: 7, len: 7 }
+        );
+        assert!(d
+            .get_disjoint_mut([0, 1, 2, 3, 4, 5, 6, 0, 1, 2, 3])
+            .is_none());
+    }
+
+    #[test]
+    fn disjoint_slot_maps() {
+        let mut sm = SlotMap::new();
+        let [a, b, c] = [1, 2, 3].map(|x| sm.insert(x));
+        let mut secondary = SecondaryMap::new();
+        secondary.insert(a, 'a');
+        secondary.insert(c, 'c');
+        sm.remove(b);
+
+        let [x, y] = sm.try_get_disjoint_mut([c, a]).unwrap();
+        std::mem::swap(x, y);
+        assert_eq!((sm[a], sm[c]), (3, 1));
+        assert_eq!(
+            sm.try_get_disjoint_mut([a, b]).unwrap_err(),
+            GetDisjointMutError::OutOfBounds { index: b, len: 2 }
+        );
+        assert_eq!(
+            sm.try_get_disjoint_mut([a, c, a]).unwrap_err(),
+            GetDisjointMutError::Overlapping(a, a)
+        );
+        assert!(sm.get_disjoint_mut([c, c]).is_none());
+
+        let [x, y] = secondary.try_get_disjoint_mut([a, c]).unwrap();
+        std::mem::swap(x, y);
+        assert_eq!((secondary[a], secondary[c]), ('c', 'a'));
+        assert_eq!(
+            secondary.try_get_disjoint_mut([b]).unwrap_err(),
+            GetDisjointMutError::OutOfBounds { index: b, len: 2 }
+        );
+        assert_eq!(
+            secondary.try_get_disjoint_mut([c, c]).unwrap_err(),
+            GetDisjointMutError::Overlapping(c, c)
+        );
+    }
+
+    #[test]
+    fn disjoint_hash_map() {
+        let mut map: HashMap<String, u32> = ["a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k.to_string(), i as u32))
+            .collect();
+        let [a, c] = map.try_get_disjoint_mut(["a", "c"]).unwrap();
+        std::mem::swap(a, c);
+        assert_eq!((map["a"], map["c"]), (2, 0));
+        assert_eq!(
+            map.try_get_disjoint_mut(["a", "x"]).unwrap_err(),
+            GetDisjointMutError::OutOfBounds { index: "x", len: 3 }
+        );
+        assert_eq!(
+            map.try_get_disjoint_mut(["b", "c", "b"]).unwrap_err(),
+            GetDisjointMutError::Overlapping("b", "b")
+        );
+        assert!(map.get_disjoint_mut(["c", "c"]).is_none());
+        assert!(map.get_disjoint_mut(["a", "b", "c"]).is_some());
+    }
+
+    #[test]
+    fn disjoint_error_messages() {
+        let out_of_bounds = GetDisjointMutError::OutOfBounds { index: 5, len: 3 };
+        assert_eq!(
+            out_of_bounds.to_string(),
+            "index 5 out of bounds for length 3"
+        );
+        let overlapping = GetDisjointMutError::Overlapping(0..3, 2..4);
+        assert_eq!(overlapping.to_string(), "indices 0..3 and 2..4 overlap");
+    }
+
+    #[test]
+    fn none_error_details() {
+        let (err, line) = (None::<u32>.some().unwrap_err(), line!());
+        assert_eq!(
+            (err.location().file(), err.location().line()),
+            (file!(), line)
+        );
+        assert_eq!((err.message(), err.value()), (None, None));
+        let at = format!(" at {}", err.location());
+        assert_eq!(
+            err.to_string(),
+            format!("some() was called on None value{at}")
+        );
+
+        let err = None::<u32>.some_with(|| "no answer").unwrap_err();
+        assert_eq!((err.message(), err.value()), (Some("no answer"), None));
+        assert_eq!(err.location().line(), line!() - 2);
+        assert_eq!(
+            Some(3).some_with(|| -> String { unreachable!() }).unwrap(),
+            3
+        );
+
+        let err = None::<u32>.some_ctx("bad line", "1 2 x").unwrap_err();
+        assert_eq!(
+            (err.message(), err.value()),
+            (Some("bad line"), Some("\"1 2 x\""))
+        );
+        let at = format!(" at {}", err.location());
+        assert_eq!(err.to_string(), format!("bad line (value: \"1 2 x\"){at}"));
+    }
+
+    #[test]
+    fn none_error_snippet_truncation() {
+        let value = |v: &str| {
+            None::<()>
+                .some_ctx("", v)
+                .unwrap_err()
+                .value()
+                .unwrap()
+                .to_owned()
+        };
+        // With the quotes, 78 characters fit the snippet exactly.
+        let fits = "a".repeat(78);
+        assert_eq!(value(&fits), format!("{fits:?}"));
+        let long = value(&"a".repeat(100));
+        assert_eq!(long, format!("\"{}...", "a".repeat(79)));
+
+        // Byte 80 falls inside a two-byte character, so cutting by bytes would panic.
+        let accented = value(&"é".repeat(100));
+        assert_eq!(accented, format!("\"{}...", "é".repeat(79)));
+        let mixed = value(&"a€😀".repeat(40));
+        assert_eq!(mixed.chars().count(), SNIPPET_LEN + 3);
+        assert!(mixed.ends_with("..."));
+    }
+
+    fn pop_all<T: Ord>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+        let mut heap = BinaryHeap::from_iter(items);
+        std::iter::from_fn(|| heap.pop()).collect()
+    }
+
+    #[test]
+    fn min_priority_order() {
+        let items = [5, 1, 4, 1, 3].map(|p| MinPriority(p, ()));
+        let order = pop_all(items).into_iter().map(|p| p.0).collect_vec();
+        assert_eq!(order, [1, 1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn tiebreak_order() {
+        let items = [(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+        let max = items.map(|(p, v)| Priority::with_tiebreak(p, v, v));
+        let order = pop_all(max).into_iter().map(|p| p.1).collect::<String>();
+        assert_eq!(order, "cadb");
+        let min = items.map(|(p, v)| MinPriority::with_tiebreak(p, v, v));
+        let order = pop_all(min).into_iter().map(|p| p.1).collect::<String>();
+        assert_eq!(order, "bdac");
+    }
+
+    #[test]
+    fn fifo_among_equal_priorities() {
+        let mut seq = Fifo::default();
+        let items = "abcdef"
+            .chars()
+            .map(|c| Priority::fifo(c < 'd', &mut seq, c));
+        let order = pop_all(items.collect_vec()).into_iter().map(|p| p.1);
+        assert_eq!(order.collect::<String>(), "abcdef");
+
+        let mut seq = Fifo::default();
+        let items = "abcdef"
+            .chars()
+            .map(|c| MinPriority::fifo(c >= 'd', &mut seq, c));
+        let order = pop_all(items.collect_vec()).into_iter().map(|p| p.1);
+        assert_eq!(order.collect::<String>(), "abcdef");
+    }
+
+    #[test]
+    fn total_f64() {
+        let nan = TotalF64(f64::NAN);
+        let neg_nan = TotalF64(-f64::NAN);
+        let values = [1.5, f64::INFINITY, -0.0, 0.0, f64::NEG_INFINITY, -2.0].map(TotalF64);
+        let mut sorted = values.iter().chain([&nan, &neg_nan]).copied().collect_vec();
+        sorted.sort();
+        let expected = [
+            -f64::NAN,
+            f64::NEG_INFINITY,
+            -2.0,
+            -0.0,
+            0.0,
+            1.5,
+            f64::INFINITY,
+        ];
+        for (value, expected) in sorted.iter().zip(expected.iter().chain([&f64::NAN])) {
+            assert_eq!(value.0.to_bits(), expected.to_bits());
+        }
+
+        assert_eq!(nan, nan);
+        assert_ne!(TotalF64(-0.0), TotalF64(0.0));
+        assert!(TotalF64(-0.0) < TotalF64(0.0));
+        // Equal values hash alike, and -0.0 and 0.0 hash apart like they compare.
+        let hasher = RandomState::new();
+        let hash = |x: TotalF64| {
+            let mut state = hasher.build_hasher();
+            x.hash(&mut state);
+            std::hash::Hasher::finish(&state)
+        };
+        assert_eq!(hash(nan), hash(TotalF64(f64::NAN)));
+        assert_ne!(hash(TotalF64(-0.0)), hash(TotalF64(0.0)));
+        let set = hashbrown::HashSet::<_>::from_iter(values.iter().chain([&nan, &nan]));
+        assert_eq!(set.len(), values.len() + 1);
+    }
+}
@@ -0,0 +1,349 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// Greatest common divisor of non-negative integers, gcd(0, 0) = 0.
+pub fn gcd<T>(mut a: T, mut b: T) -> T
+where
+    T: Copy + Eq + From<u8> + Rem<Output = T>,
+{
+    while b != T::from(0) {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple of non-negative integers, zero if either is zero.
+pub fn lcm<T>(a: T, b: T) -> T
+where
+    T: Copy + Eq + From<u8> + Rem<Output = T> + Div<Output = T> + Mul<Output = T>,
+{
+    if a == T::from(0) || b == T::from(0) {
+        return T::from(0);
+    }
+    a / gcd(a, b) * b
+}
+
+pub fn gcd_all<T, I>(iter: I) -> T
+where
+    T: Copy + Eq + From<u8> + Rem<Output = T>,
+    I: IntoIterator<Item = T>,
+{
+    iter.into_iter().fold(T::from(0), gcd)
+}
+
+/// Least common multiple of all items, one for an empty iterator.
+pub fn lcm_all<T, I>(iter: I) -> T
+where
+    T: Copy + Eq + From<u8> + Rem<Output = T> + Div<Output = T> + Mul<Output = T>,
+    I: IntoIterator<Item = T>,
+{
+    iter.into_iter().fold(T::from(1), lcm)
+}
+
+fn ext_gcd_wide(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// Returns (g, x, y) such that a * x + b * y = g = gcd(a, b) with g >= 0.
+pub fn ext_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    let (g, x, y) = ext_gcd_wide(a as i128, b as i128);
+    (g as i64, x as i64, y as i64)
+}
+
+/// Inverse of a modulo m, if a and m are coprime. `None` for m = 0.
+pub fn mod_inv(a: u64, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+    let (g, x, _) = ext_gcd_wide((a % m) as i128, m as i128);
+    (g == 1).then(|| x.rem_euclid(m as i128) as u64)
+}
+
+/// base^exp modulo m.
+///
+/// # Panics
+///
+/// Panics if m is 0.
+pub fn mod_pow(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    assert!(m > 0, "modulus must be positive");
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = (result as u128 * base as u128 % m as u128) as u64;
+        }
+        base = (base as u128 * base as u128 % m as u128) as u64;
+        exp >>= 1;
+    }
+    result
+}
+
+/// Solves the system x = r (mod m) for all given (r, m) pairs, returning (x, lcm of moduli)
+/// with 0 <= x < lcm. Moduli need not be coprime, `None` is returned if the congruences are
+/// inconsistent, a modulus is 0 or the combined modulus overflows.
+pub fn crt<I: IntoIterator<Item = (u64, u64)>>(congruences: I) -> Option<(u64, u64)> {
+    let (mut x, mut m) = (0i128, 1i128);
+    for (r2, m2) in congruences {
+        if m2 == 0 {
+            return None;
+        }
+        let (r2, m2) = (r2 as i128 % m2 as i128, m2 as i128);
+        let g = gcd(m, m2);
+        if (r2 - x) % g != 0 {
+            return None;
+        }
+
+        // Solve x + m * k = r2 (mod m2) for k.
+        let (m_g, m2_g) = (m / g, m2 / g);
+        let (_, inv, _) = ext_gcd_wide(m_g % m2_g, m2_g);
+        let diff = ((r2 - x) / g).rem_euclid(m2_g) as u128;
+        let k = (diff * inv.rem_euclid(m2_g) as u128 % m2_g as u128) as i128;
+        let lcm = m.checked_mul(m2_g).filter(|l| *l <= u64::MAX as i128)?;
+        x = (x + m * k).rem_euclid(lcm);
+        m = lcm;
+    }
+    Some((x as u64, m as u64))
+}
+
+/// Integer modulo the constant M, which must be positive and less than 2^63 so that sums of
+/// two values don't overflow. Constructing one with any other M fails to compile.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mod<const M: u64>(u64);
+
+impl<const M: u64> Default for Mod<M> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<const M: u64> Mod<M> {
+    pub fn new(x: u64) -> Self {
+        const { assert!(M > 0 && M < 1 << 63, "modulus out of range") }
+        Self(x % M)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    pub fn pow(self, exp: u64) -> Self {
+        Self(mod_pow(self.0, exp, M))
+    }
+
+    pub fn inv(self) -> Option<Self> {
+        mod_inv(self.0, M).map(Self)
+    }
+}
+
+impl<const M: u64> From<u64> for Mod<M> {
+    fn from(x: u64) -> Self {
+        Self::new(x)
+    }
+}
+
+impl<const M: u64> From<i64> for Mod<M> {
+    fn from(x: i64) -> Self {
+        const { assert!(M > 0 && M < 1 << 63, "modulus out of range") }
+        Self(x.rem_euclid(M as i64) as u64)
+    }
+}
+
+impl<const M: u64> Add for Mod<M> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let s = self.0 + rhs.0;
+        Self(if s >= M { s - M } else { s })
+    }
+}
+
+impl<const M: u64> Sub for Mod<M> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<const M: u64> Neg for Mod<M> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(if self.0 == 0 { 0 } else { M - self.0 })
+    }
+}
+
+impl<const M: u64> Mul for Mod<M> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self((self.0 as u128 * rhs.0 as u128 % M as u128) as u64)
+    }
+}
+
+impl<const M: u64> AddAssign for Mod<M> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const M: u64> SubAssign for Mod<M> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const M: u64> MulAssign for Mod<M> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const M: u64> std::iter::Sum for Mod<M> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::new(0), Add::add)
+    }
+}
+
+impl<const M: u64> std::iter::Product for Mod<M> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::new(1), Mul::mul)
+    }
+}
+
+impl<const M: u64> fmt::Debug for Mod<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (mod {M})", self.0)
+    }
+}
+
+impl<const M: u64> fmt::Display for Mod<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn crt_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(36);
+        for _ in 0..2000 {
+            // Small moduli share factors often, and include 1.
+            let congruences = (0..rng.gen_range(0..4))
+                .map(|_| {
+                    let m = rng.gen_range(1..=12);
+                    (rng.gen_range(0..2 * m), m)
+                })
+                .collect::<Vec<_>>();
+            let l = lcm_all(congruences.iter().map(|&(_, m)| m));
+            let x = (0..l).find(|x| congruences.iter().all(|&(r, m)| x % m == r % m));
+            assert_eq!(
+                crt(congruences.clone()),
+                x.map(|x| (x, l)),
+                "{congruences:?}"
+            );
+        }
+        assert_eq!(crt([(3, 4), (1, 6)]), Some((7, 12)));
+        assert_eq!(crt([(3, 4), (2, 6)]), None);
+        assert_eq!(crt([(5, 1), (0, 1)]), Some((0, 1)));
+        assert_eq!(crt([(1, 1 << 40), (0, (1 << 40) - 1), (0, 3)]), None);
+        assert_eq!(crt([(0, 3), (0, 0)]), None);
+    }
+
+    #[test]
+    fn mod_inv_against_brute_force() {
+        for m in 1..40 {
+            for a in 0..2 * m {
+                let inv = (0..m).find(|x| a * x % m == 1 % m);
+                assert_eq!(mod_inv(a, m), inv, "{a} mod {m}");
+            }
+        }
+        assert_eq!(mod_inv(6, 9), None);
+        assert_eq!(mod_inv(0, 7), None);
+        assert_eq!(mod_inv(3, 0), None);
+        let p = (1 << 61) - 1;
+        assert_eq!(mod_inv(p - 1, p), Some(p - 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus must be positive")]
+    fn mod_pow_zero_modulus() {
+        mod_pow(2, 3, 0);
+    }
+
+    #[test]
+    fn mod_pow_and_ext_gcd() {
+        for m in 1..20u64 {
+            for base in 0..30 {
+                let mut expected = 1 % m;
+                for exp in 0..10 {
+                    assert_eq!(mod_pow(base, exp, m), expected, "{base}^{exp} mod {m}");
+                    expected = expected * base % m;
+                }
+            }
+        }
+        assert_eq!(mod_pow(2, 64, u64::MAX), 1);
+        assert_eq!(mod_pow(u64::MAX - 1, 2, u64::MAX), 1);
+
+        let mut rng = StdRng::seed_from_u64(36);
+        for _ in 0..1000 {
+            let (a, b) = (rng.gen_range(-1000..=1000), rng.gen_range(-1000..=1000));
+            let (g, x, y) = ext_gcd(a, b);
+            assert_eq!(g, gcd(a.abs(), b.abs()), "gcd({a}, {b})");
+            assert_eq!(a * x + b * y, g, "gcd({a}, {b})");
+        }
+    }
+
+    #[test]
+    fn mod_arithmetic() {
+        type M7 = Mod<7>;
+        for a in -20i64..20 {
+            for b in -20i64..20 {
+                let (x, y) = (M7::from(a), M7::from(b));
+                assert_eq!((x + y).value(), (a + b).rem_euclid(7) as u64);
+                assert_eq!((x - y).value(), (a - b).rem_euclid(7) as u64);
+                assert_eq!((x * y).value(), (a * b).rem_euclid(7) as u64);
+            }
+            let x = M7::from(a);
+            assert_eq!((-x).value(), (-a).rem_euclid(7) as u64);
+            assert_eq!(
+                x.inv().map(|inv| inv * x),
+                (x != M7::new(0)).then(|| M7::new(1))
+            );
+        }
+        assert_eq!(Mod::<6>::new(4).inv(), None);
+        assert_eq!(M7::new(3).pow(6), M7::new(1));
+        assert_eq!((1..=6).map(M7::new).product::<M7>(), M7::from(-1i64));
+        assert_eq!((1..=6).map(M7::new).sum::<M7>(), M7::new(0));
+        assert_eq!(Mod::<1>::new(5).value(), 0);
+        assert_eq!(M7::default(), M7::new(0));
+        assert_eq!(std::iter::empty::<M7>().sum::<M7>(), M7::default());
+
+        // Values near the largest allowed modulus must not overflow.
+        const BIG: u64 = (1 << 63) - 25;
+        let big = Mod::<BIG>::new(BIG - 1);
+        assert_eq!((big + big).value(), BIG - 2);
+        assert_eq!((big * big).value(), 1);
+        assert_eq!(Mod::<BIG>::from(-1i64), big);
+        assert_eq!(format!("{big:?}"), format!("{} (mod {BIG})", BIG - 1));
+    }
+}
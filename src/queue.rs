@@ -0,0 +1,572 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::{MinPriority, Priority};
+
+/// Max-priority queue interface shared by `BinaryHeap` and the integer priority queues in this
+/// module, over `Priority` or `MinPriority` items.
+pub trait PriorityQueue<I> {
+    fn push(&mut self, item: I);
+    fn pop(&mut self) -> Option<I>;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<I: Ord> PriorityQueue<I> for BinaryHeap<I> {
+    fn push(&mut self, item: I) {
+        BinaryHeap::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<I> {
+        BinaryHeap::pop(self)
+    }
+
+    fn len(&self) -> usize {
+        BinaryHeap::len(self)
+    }
+}
+
+/// Priorities that map to an order-preserving integer of at most `BITS` bits.
+pub trait IntPriority: Copy + Ord {
+    const BITS: u32;
+
+    fn to_bits(self) -> u64;
+}
+
+macro_rules! impl_int_priority {
+    ($($t:ty),*) => {
+        $(impl IntPriority for $t {
+            const BITS: u32 = <$t>::BITS;
+
+            #[inline]
+            fn to_bits(self) -> u64 {
+                self as u64
+            }
+        })*
+    };
+}
+
+impl_int_priority!(u8, u16, u32, u64, usize);
+
+impl<K: IntPriority> IntPriority for Reverse<K> {
+    const BITS: u32 = K::BITS;
+
+    #[inline]
+    fn to_bits(self) -> u64 {
+        !self.0.to_bits() & (u64::MAX >> (64 - K::BITS))
+    }
+}
+
+// Lexicographic order by packing both halves into one integer.
+impl<A: IntPriority, B: IntPriority> IntPriority for (A, B) {
+    const BITS: u32 = {
+        assert!(A::BITS + B::BITS <= 64, "packed priority too wide");
+        A::BITS + B::BITS
+    };
+
+    #[inline]
+    fn to_bits(self) -> u64 {
+        // Referencing BITS forces the width check at compile time.
+        (self.0.to_bits() << (Self::BITS - A::BITS)) | self.1.to_bits()
+    }
+}
+
+/// Queue items with an integer key, the item with the largest key pops first.
+pub trait IntKeyed {
+    fn int_key(&self) -> u64;
+}
+
+impl<P: IntPriority, T> IntKeyed for Priority<P, T> {
+    #[inline]
+    fn int_key(&self) -> u64 {
+        self.0.to_bits()
+    }
+}
+
+impl<P: IntPriority, T> IntKeyed for MinPriority<P, T> {
+    #[inline]
+    fn int_key(&self) -> u64 {
+        Reverse(self.0).to_bits()
+    }
+}
+
+/// Priorities ordered first by an integer, which picks their bucket in a `BucketQueue`.
+/// Anything after it only breaks ties within a bucket.
+pub trait BucketPriority: Ord {
+    const BITS: u32;
+
+    /// Order-preserving: a higher bucket never holds a lower priority.
+    fn bucket(&self) -> u64;
+}
+
+macro_rules! impl_bucket_priority {
+    ($($t:ty),*) => {
+        $(impl BucketPriority for $t {
+            const BITS: u32 = <$t>::BITS;
+
+            #[inline]
+            fn bucket(&self) -> u64 {
+                *self as u64
+            }
+        })*
+    };
+}
+
+impl_bucket_priority!(u8, u16, u32, u64, usize);
+
+impl<K: BucketPriority> BucketPriority for Reverse<K> {
+    const BITS: u32 = K::BITS;
+
+    #[inline]
+    fn bucket(&self) -> u64 {
+        !self.0.bucket() & (u64::MAX >> (64 - K::BITS))
+    }
+}
+
+impl<A: BucketPriority, B: Ord> BucketPriority for (A, B) {
+    const BITS: u32 = A::BITS;
+
+    #[inline]
+    fn bucket(&self) -> u64 {
+        self.0.bucket()
+    }
+}
+
+/// Queue items with a bucket, items in higher buckets pop first.
+pub trait Bucketed: Ord {
+    fn bucket(&self) -> u64;
+}
+
+impl<P: BucketPriority, T> Bucketed for Priority<P, T> {
+    #[inline]
+    fn bucket(&self) -> u64 {
+        self.0.bucket()
+    }
+}
+
+impl<P: BucketPriority, T> Bucketed for MinPriority<P, T> {
+    #[inline]
+    fn bucket(&self) -> u64 {
+        !self.0.bucket() & (u64::MAX >> (64 - P::BITS))
+    }
+}
+
+/// Most buckets a `BucketQueue` holds at once, bounding its memory.
+const MAX_BUCKETS: u64 = 1 << 24;
+
+/// Monotone bucket queue, with one bucket per value of the primary priority from the last
+/// popped one down. Like `RadixHeap`, pushed priorities should not exceed the last popped one;
+/// pushes that do go to the current bucket. Each bucket is a heap ordering its items by their
+/// full priority, so tie-breaks cost O(log n) within a bucket only. Pops move the cursor past
+/// empty buckets, which costs O(span) in total for primary priorities spanning `span`
+/// values. Panics if the primary priorities of stored items span more than 2^24 values.
+pub struct BucketQueue<I> {
+    /// The buckets from the cursor down. Those past the lowest item are empty and reused.
+    buckets: VecDeque<BinaryHeap<I>>,
+    /// Primary priority of the front bucket, set by the first push and never increasing.
+    cursor: Option<u64>,
+    len: usize,
+}
+
+impl<I> Default for BucketQueue<I> {
+    fn default() -> Self {
+        Self {
+            buckets: VecDeque::new(),
+            cursor: None,
+            len: 0,
+        }
+    }
+}
+
+impl<I: Bucketed> BucketQueue<I> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, item: I) {
+        let bucket = item.bucket();
+        let cursor = *self.cursor.get_or_insert(bucket);
+        let i = cursor.saturating_sub(bucket);
+        assert!(
+            i < MAX_BUCKETS,
+            "priorities span more than {MAX_BUCKETS} buckets"
+        );
+        let i = i as usize;
+        if i >= self.buckets.len() {
+            self.buckets.resize_with(i + 1, BinaryHeap::new);
+        }
+        self.buckets[i].push(item);
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<I> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.buckets[0].is_empty() {
+            self.buckets.rotate_left(1);
+            self.cursor = self.cursor.map(|c| c - 1);
+        }
+        self.len -= 1;
+        self.buckets[0].pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<I: Bucketed> PriorityQueue<I> for BucketQueue<I> {
+    fn push(&mut self, item: I) {
+        BucketQueue::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<I> {
+        BucketQueue::pop(self)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Radix heap, a monotone priority queue: pushed priorities should not exceed the last popped
+/// one. Pushes that do are clamped to the last popped priority, so they pop before anything
+/// lower, but no longer in order among themselves. Popped items keep their original priority.
+pub struct RadixHeap<I> {
+    // Stored keys are inverted so the heap internally pops the minimum.
+    buckets: [Vec<(u64, I)>; 65],
+    last: u64,
+    len: usize,
+}
+
+impl<I> Default for RadixHeap<I> {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| Vec::new()),
+            last: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<I: IntKeyed> RadixHeap<I> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    fn bucket(&self, key: u64) -> usize {
+        64 - (key ^ self.last).leading_zeros() as usize
+    }
+
+    pub fn push(&mut self, item: I) {
+        let key = (!item.int_key()).max(self.last);
+        let b = self.bucket(key);
+        self.buckets[b].push((key, item));
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<I> {
+        if self.buckets[0].is_empty() {
+            let b = self.buckets.iter().position(|b| !b.is_empty())?;
+            let moved = std::mem::take(&mut self.buckets[b]);
+            self.last = moved.iter().map(|(k, _)| *k).min().unwrap();
+            for (key, item) in moved {
+                let nb = self.bucket(key);
+                self.buckets[nb].push((key, item));
+            }
+        }
+        self.len -= 1;
+        self.buckets[0].pop().map(|(_, item)| item)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<I: IntKeyed> PriorityQueue<I> for RadixHeap<I> {
+    fn push(&mut self, item: I) {
+        RadixHeap::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<I> {
+        RadixHeap::pop(self)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Keeps the `k` largest items pushed so far in a size-k min-heap, using O(k) memory however
+/// many items are pushed.
+#[derive(Clone, Debug)]
+pub struct TopK<T> {
+    k: usize,
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> TopK<T> {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse(item));
+        } else if let Some(mut smallest) = self.heap.peek_mut() {
+            if item > smallest.0 {
+                smallest.0 = item;
+            }
+        }
+    }
+
+    /// The smallest kept item, which a new item must exceed to be kept once `k` are kept.
+    pub fn threshold(&self) -> Option<&T> {
+        self.heap.peek().map(|r| &r.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// The kept items in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.heap.iter().map(|r| &r.0)
+    }
+
+    /// The kept items, largest first.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|r| r.0)
+            .collect()
+    }
+}
+
+impl<T: Ord> Extend<T> for TopK<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn drain<I, Q: PriorityQueue<I>>(queue: &mut Q) -> Vec<I> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn tiebreaks_within_buckets() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut bucket = BucketQueue::new();
+        bucket.push(Priority((1u32, 0u32), 0));
+        bucket.push(Priority((0, 0), 1));
+        bucket.push(Priority((1, u32::MAX), 2));
+        assert_eq!(
+            drain(&mut bucket).iter().map(|p| p.1).collect::<Vec<_>>(),
+            [2, 0, 1]
+        );
+
+        // Tie-breaks of any width and type, only the primary priority picks the bucket.
+        let mut bucket = BucketQueue::new();
+        let mut heap = BinaryHeap::new();
+        for i in 0..2000 {
+            let priority = (rng.gen_range(0..100u64), (rng.gen::<u64>(), i.to_string()));
+            heap.push(Priority(priority.clone(), i));
+            bucket.push(Priority(priority, i));
+        }
+        let expected = drain(&mut heap)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        let popped = drain(&mut bucket)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        assert_eq!(popped, expected);
+        assert!(bucket.is_empty());
+    }
+
+    #[test]
+    fn min_priority_tiebreak() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut heap = BinaryHeap::new();
+        let mut bucket = BucketQueue::new();
+        for i in 0..2000 {
+            let (f, t) = (rng.gen_range(0..50u16), rng.gen::<u16>());
+            heap.push(MinPriority::with_tiebreak(f, Reverse(t), i));
+            bucket.push(MinPriority::with_tiebreak(f, Reverse(t), i));
+        }
+        let expected = drain(&mut heap)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        assert!(expected.windows(2).all(|w| w[0] <= w[1]));
+        let popped = drain(&mut bucket)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn bucket_queue_monotone() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut heap = BinaryHeap::new();
+        let mut bucket = BucketQueue::new();
+        let mut last = (1000u32, u32::MAX);
+        for i in 0..5000 {
+            if rng.gen_bool(0.4) {
+                if let Some(Priority(p, _)) = heap.pop() {
+                    assert_eq!(bucket.pop().map(|p: Priority<_, _>| p.0), Some(p));
+                    last = p;
+                }
+            } else {
+                // Never above the last popped priority, and within 20 of it.
+                let first = rng.gen_range(last.0.saturating_sub(20)..=last.0);
+                let priority = (first, rng.gen::<u32>()).min(last);
+                heap.push(Priority(priority, i));
+                bucket.push(Priority(priority, i));
+            }
+            assert_eq!(bucket.len(), heap.len());
+        }
+        assert!(bucket.buckets.len() <= 22);
+        let expected = drain(&mut heap)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        let popped = drain(&mut bucket)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn bucket_queue_clamps_higher_pushes() {
+        let mut bucket = BucketQueue::new();
+        bucket.push(Priority(5u8, 0));
+        bucket.push(Priority(3, 1));
+        assert_eq!(bucket.pop().map(|p| p.1), Some(0));
+        // Above the last popped priority, so it goes to the current bucket.
+        bucket.push(Priority(9, 2));
+        bucket.push(Priority(4, 3));
+        let order = drain(&mut bucket)
+            .into_iter()
+            .map(|p| p.1)
+            .collect::<Vec<_>>();
+        assert_eq!(order, [2, 3, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "priorities span more than")]
+    fn bucket_queue_rejects_wide_spans() {
+        let mut bucket = BucketQueue::new();
+        bucket.push(Priority(u32::MAX, 0));
+        bucket.push(Priority(0, 1));
+    }
+
+    #[test]
+    fn radix_heap_monotone() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut heap = BinaryHeap::new();
+        let mut radix = RadixHeap::new();
+        let mut last = (u32::MAX, u32::MAX);
+        for i in 0..5000 {
+            if rng.gen_bool(0.4) {
+                if let Some(Priority(p, _)) = heap.pop() {
+                    assert_eq!(radix.pop().map(|p: Priority<_, _>| p.0), Some(p));
+                    last = p;
+                }
+            } else {
+                // Never above the last popped priority.
+                let priority = (rng.gen_range(0..=last.0), rng.gen::<u32>());
+                let priority = priority.min(last);
+                heap.push(Priority(priority, i));
+                radix.push(Priority(priority, i));
+            }
+        }
+        let expected = drain(&mut heap)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        let popped = drain(&mut radix)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn top_k_against_sorting() {
+        let mut rng = StdRng::seed_from_u64(41);
+        for _ in 0..500 {
+            // Few distinct values make duplicates common.
+            let items = (0..rng.gen_range(0..40))
+                .map(|_| rng.gen_range(0..10))
+                .collect::<Vec<u32>>();
+            let k = rng.gen_range(0..50);
+            let mut top = TopK::new(k);
+            top.extend(items.iter().copied());
+
+            let mut expected = items.clone();
+            expected.sort_unstable_by(|a, b| b.cmp(a));
+            expected.truncate(k);
+            assert_eq!(top.len(), expected.len());
+            assert_eq!(top.is_empty(), expected.is_empty());
+            assert_eq!(top.threshold(), expected.last());
+            let mut kept = top.iter().copied().collect::<Vec<_>>();
+            kept.sort_unstable_by(|a, b| b.cmp(a));
+            assert_eq!(kept, expected);
+            assert_eq!(top.into_sorted_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn top_k_edge_cases() {
+        let mut none = TopK::new(0);
+        none.extend([3, 1, 2]);
+        assert!(none.is_empty());
+        assert_eq!(none.threshold(), None);
+        assert_eq!(none.into_sorted_vec(), Vec::<i32>::new());
+
+        let mut all = TopK::new(10);
+        all.extend([3, 1, 3, 2]);
+        assert_eq!(all.threshold(), Some(&1));
+        assert_eq!(all.into_sorted_vec(), [3, 3, 2, 1]);
+
+        // Items equal to the threshold don't replace it.
+        let mut ties = TopK::new(2);
+        ties.extend([5, 5, 5, 4, 6]);
+        assert_eq!(ties.into_sorted_vec(), [6, 5]);
+    }
+}
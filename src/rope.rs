@@ -0,0 +1,370 @@
+use std::mem;
+
+use rand::Rng;
+use slotmap::{new_key_type, Key, SlotMap};
+
+new_key_type! { pub struct RopeKey; }
+
+struct RopeNode<T> {
+    value: T,
+    priority: u32,
+    left: RopeKey,
+    right: RopeKey,
+    count: usize,
+    /// The children of this subtree still have to be swapped, recursively.
+    reversed: bool,
+}
+
+/// A sequence stored in a [`Ropes`] arena. Handles are not `Clone`, so each tree has exactly
+/// one owner; splitting hands out a new handle and appending consumes one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Rope {
+    root: RopeKey,
+}
+
+/// Implicit treaps sharing one node arena, so nodes can move between sequences. Splitting,
+/// appending and reversing cost O(log n), reversal is applied lazily.
+pub struct Ropes<T> {
+    nm: SlotMap<RopeKey, RopeNode<T>>,
+}
+
+impl<T> Default for Ropes<T> {
+    fn default() -> Self {
+        Self {
+            nm: SlotMap::with_key(),
+        }
+    }
+}
+
+impl<T> Ropes<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn count(&self, node: RopeKey) -> usize {
+        self.nm.get(node).map(|n| n.count).unwrap_or(0)
+    }
+
+    fn update(&mut self, node: RopeKey) {
+        let RopeNode { left, right, .. } = self.nm[node];
+        self.nm[node].count = 1 + self.count(left) + self.count(right);
+    }
+
+    fn push_down(&mut self, node: RopeKey) {
+        let n = &mut self.nm[node];
+        if !mem::take(&mut n.reversed) {
+            return;
+        }
+        mem::swap(&mut n.left, &mut n.right);
+        let (left, right) = (n.left, n.right);
+        for child in [left, right] {
+            if let Some(c) = self.nm.get_mut(child) {
+                c.reversed ^= true;
+            }
+        }
+    }
+
+    fn split(&mut self, node: RopeKey, rank: usize) -> (RopeKey, RopeKey) {
+        if node.is_null() {
+            return (RopeKey::null(), RopeKey::null());
+        }
+
+        self.push_down(node);
+        let RopeNode { left, right, .. } = self.nm[node];
+        let left_count = self.count(left);
+        if rank <= left_count {
+            let (ll, lr) = self.split(left, rank);
+            self.nm[node].left = lr;
+            self.update(node);
+            (ll, node)
+        } else {
+            let (rl, rr) = self.split(right, rank - left_count - 1);
+            self.nm[node].right = rl;
+            self.update(node);
+            (node, rr)
+        }
+    }
+
+    fn merge(&mut self, left: RopeKey, right: RopeKey) -> RopeKey {
+        match (self.nm.get(left), self.nm.get(right)) {
+            (Some(l), Some(r)) => {
+                if l.priority < r.priority {
+                    self.push_down(left);
+                    let l_right = self.nm[left].right;
+                    self.nm[left].right = self.merge(l_right, right);
+                    self.update(left);
+                    left
+                } else {
+                    self.push_down(right);
+                    let r_left = self.nm[right].left;
+                    self.nm[right].left = self.merge(left, r_left);
+                    self.update(right);
+                    right
+                }
+            },
+            (None, Some(_)) => right,
+            _ => left,
+        }
+    }
+
+    /// Builds a rope holding `values` in order, in O(n).
+    pub fn rope<I, R>(&mut self, values: I, rng: &mut R) -> Rope
+    where
+        I: IntoIterator<Item = T>,
+        R: Rng,
+    {
+        // Keep the right spine on a stack. A new node becomes the right child of the last
+        // spine node with a smaller priority, the nodes popped above it its left subtree.
+        let mut spine: Vec<RopeKey> = Vec::new();
+        for value in values {
+            let priority = rng.gen();
+            let mut left = RopeKey::null();
+            while let Some(&top) = spine.last() {
+                if self.nm[top].priority <= priority {
+                    break;
+                }
+                left = spine.pop().unwrap();
+                self.update(left);
+            }
+            let node = self.nm.insert(RopeNode {
+                value,
+                priority,
+                left,
+                right: RopeKey::null(),
+                count: 1,
+                reversed: false,
+            });
+            if let Some(&top) = spine.last() {
+                self.nm[top].right = node;
+            }
+            spine.push(node);
+        }
+
+        let root = spine.first().copied().unwrap_or_default();
+        while let Some(node) = spine.pop() {
+            self.update(node);
+        }
+        Rope { root }
+    }
+
+    pub fn len(&self, rope: &Rope) -> usize {
+        self.count(rope.root)
+    }
+
+    pub fn is_empty(&self, rope: &Rope) -> bool {
+        rope.root.is_null()
+    }
+
+    /// Splits `rope` before `at`, returning the values from `at` on as a new rope.
+    pub fn split_off(&mut self, rope: &mut Rope, at: usize) -> Rope {
+        let (left, right) = self.split(rope.root, at);
+        rope.root = left;
+        Rope { root: right }
+    }
+
+    /// Moves the values of `other` to the end of `rope`.
+    pub fn append(&mut self, rope: &mut Rope, other: Rope) {
+        rope.root = self.merge(rope.root, other.root);
+    }
+
+    /// Reverses `rope` in O(1), the work is done by later splits and merges.
+    pub fn reverse(&mut self, rope: &mut Rope) {
+        if let Some(n) = self.nm.get_mut(rope.root) {
+            n.reversed ^= true;
+        }
+    }
+
+    /// Removes the values of `rope` from the arena and returns them in order.
+    pub fn into_vec(&mut self, rope: Rope) -> Vec<T> {
+        let order = self.iter(&rope).map(|(node, _)| node).collect::<Vec<_>>();
+        order
+            .into_iter()
+            .map(|node| self.nm.remove(node).unwrap().value)
+            .collect()
+    }
+
+    /// Returns the value at `rank`. Pending reversals are accounted for on the way down, so
+    /// this doesn't need `&mut self`.
+    pub fn get(&self, rope: &Rope, mut rank: usize) -> Option<&T> {
+        let mut cur = rope.root;
+        let mut flip = false;
+        while let Some(c) = self.nm.get(cur) {
+            flip ^= c.reversed;
+            let (left, right) = if flip {
+                (c.right, c.left)
+            } else {
+                (c.left, c.right)
+            };
+            let left_count = self.count(left);
+            match rank.checked_sub(left_count) {
+                None => cur = left,
+                Some(0) => return Some(&c.value),
+                Some(r) => {
+                    cur = right;
+                    rank = r - 1;
+                },
+            }
+        }
+        None
+    }
+
+    pub fn first(&self, rope: &Rope) -> Option<&T> {
+        self.get(rope, 0)
+    }
+
+    pub fn last(&self, rope: &Rope) -> Option<&T> {
+        self.get(rope, self.len(rope).checked_sub(1)?)
+    }
+
+    /// Iterates over the values of `rope` in order.
+    pub fn values<'a>(&'a self, rope: &Rope) -> impl Iterator<Item = &'a T> + 'a {
+        self.iter(rope).map(|(_, value)| value)
+    }
+
+    fn iter<'a>(&'a self, rope: &Rope) -> Iter<'a, T> {
+        let mut iter = Iter {
+            ropes: self,
+            stack: Vec::new(),
+        };
+        iter.push_left(rope.root, false);
+        iter
+    }
+
+    fn check_subtree(&self, node: RopeKey) -> (usize, u32) {
+        let Some(n) = self.nm.get(node) else {
+            return (0, u32::MAX);
+        };
+        let (left_count, left_priority) = self.check_subtree(n.left);
+        let (right_count, right_priority) = self.check_subtree(n.right);
+        assert!(
+            n.priority <= left_priority && n.priority <= right_priority,
+            "heap order violated"
+        );
+        let count = 1 + left_count + right_count;
+        assert!(n.count == count, "bad subtree count");
+        (count, n.priority)
+    }
+
+    /// Verifies heap order and subtree counts of `rope`, panicking if either is violated.
+    pub fn check_invariants(&self, rope: &Rope) {
+        self.check_subtree(rope.root);
+    }
+}
+
+/// In-order traversal that tracks pending reversals instead of applying them.
+struct Iter<'a, T> {
+    ropes: &'a Ropes<T>,
+    /// Nodes whose left subtree has been visited, with whether their subtree is flipped.
+    stack: Vec<(RopeKey, bool)>,
+}
+
+impl<T> Iter<'_, T> {
+    fn children(&self, node: RopeKey, flip: bool) -> (RopeKey, RopeKey) {
+        let n = &self.ropes.nm[node];
+        if flip {
+            (n.right, n.left)
+        } else {
+            (n.left, n.right)
+        }
+    }
+
+    fn push_left(&mut self, mut node: RopeKey, mut flip: bool) {
+        while let Some(n) = self.ropes.nm.get(node) {
+            flip ^= n.reversed;
+            self.stack.push((node, flip));
+            node = self.children(node, flip).0;
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (RopeKey, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, flip) = self.stack.pop()?;
+        let right = self.children(node, flip).1;
+        self.push_left(right, flip);
+        Some((node, &self.ropes.nm[node].value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn check_against_model(ropes: &Ropes<u32>, rope: &Rope, model: &[u32]) {
+        ropes.check_invariants(rope);
+        assert_eq!(ropes.len(rope), model.len());
+        assert_eq!(ropes.is_empty(rope), model.is_empty());
+        assert!(ropes.values(rope).eq(model));
+        for (i, v) in model.iter().enumerate() {
+            assert_eq!(ropes.get(rope, i), Some(v));
+        }
+        assert_eq!(ropes.get(rope, model.len()), None);
+        assert_eq!(ropes.first(rope), model.first());
+        assert_eq!(ropes.last(rope), model.last());
+    }
+
+    #[test]
+    fn random_operations() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut ropes = Ropes::new();
+            let mut models = (0..rng.gen_range(1..6))
+                .map(|i| (0..rng.gen_range(0..40)).map(|j| 100 * i + j).collect())
+                .collect::<Vec<Vec<u32>>>();
+            let mut rps = models
+                .iter()
+                .map(|m| ropes.rope(m.iter().copied(), &mut rng))
+                .collect::<Vec<_>>();
+            for _ in 0..300 {
+                let from = rng.gen_range(0..models.len());
+                let to = rng.gen_range(0..models.len());
+                let at = rng.gen_range(0..=models[from].len());
+                match rng.gen_range(0..4) {
+                    0 => {
+                        ropes.reverse(&mut rps[from]);
+                        models[from].reverse();
+                    },
+                    // Split and append back, possibly onto the same rope.
+                    op => {
+                        let mut moved = ropes.split_off(&mut rps[from], at);
+                        let mut moved_model = models[from].split_off(at);
+                        check_against_model(&ropes, &moved, &moved_model);
+                        if op == 1 {
+                            ropes.reverse(&mut moved);
+                            moved_model.reverse();
+                        }
+                        ropes.append(&mut rps[to], moved);
+                        models[to].extend(moved_model);
+                    },
+                }
+                for (rope, model) in rps.iter().zip(&models) {
+                    check_against_model(&ropes, rope, model);
+                }
+            }
+
+            // Draining every rope must leave the arena empty.
+            for (rope, model) in rps.into_iter().zip(models) {
+                assert_eq!(ropes.into_vec(rope), model);
+            }
+            assert!(ropes.nm.is_empty());
+        }
+    }
+
+    #[test]
+    fn empty_ropes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut ropes = Ropes::<u32>::new();
+        let mut rope = ropes.rope([], &mut rng);
+        check_against_model(&ropes, &rope, &[]);
+        ropes.reverse(&mut rope);
+        let rest = ropes.split_off(&mut rope, 0);
+        check_against_model(&ropes, &rest, &[]);
+        ropes.append(&mut rope, rest);
+        assert_eq!(ropes.into_vec(rope), []);
+    }
+}
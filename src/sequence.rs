@@ -0,0 +1,541 @@
+use crate::treap::{NodeKey, Treap};
+
+/// A sequence supporting insertion at a rank and lookup of elements through stable handles.
+pub trait PositionalSequence {
+    type Item;
+    type Handle: Copy;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert_at(&mut self, value: Self::Item, rank: usize) -> Self::Handle;
+
+    /// Removes the element, returning it along with its rank before removal.
+    fn remove(&mut self, handle: Self::Handle) -> Option<(Self::Item, usize)>;
+
+    /// Moves the element to `new_rank` (counted after its removal) without invalidating its
+    /// handle. Returns the old rank.
+    fn move_to(&mut self, handle: Self::Handle, new_rank: usize) -> Option<usize>;
+
+    fn rank_of(&self, handle: Self::Handle) -> Option<usize>;
+
+    fn get(&self, handle: Self::Handle) -> Option<&Self::Item>;
+
+    fn get_at(&self, rank: usize) -> Option<&Self::Item>;
+}
+
+impl<T> PositionalSequence for Treap<T> {
+    type Item = T;
+    type Handle = NodeKey;
+
+    fn len(&self) -> usize {
+        Treap::len(self)
+    }
+
+    fn insert_at(&mut self, value: T, rank: usize) -> NodeKey {
+        self.insert(value, rank, &mut rand::thread_rng())
+    }
+
+    fn remove(&mut self, handle: NodeKey) -> Option<(T, usize)> {
+        Treap::remove(self, handle)
+    }
+
+    fn move_to(&mut self, handle: NodeKey, new_rank: usize) -> Option<usize> {
+        Treap::move_to(self, handle, new_rank)
+    }
+
+    fn rank_of(&self, handle: NodeKey) -> Option<usize> {
+        self.rank(handle)
+    }
+
+    fn get(&self, handle: NodeKey) -> Option<&T> {
+        Treap::get(self, handle)
+    }
+
+    fn get_at(&self, rank: usize) -> Option<&T> {
+        self.get_by_rank(rank)
+    }
+}
+
+// Values addressed by a plain index handle, with a free list for reuse.
+struct HandleSlab<T> {
+    values: Vec<Option<T>>,
+    free: Vec<usize>,
+}
+
+impl<T> Default for HandleSlab<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<T> HandleSlab<T> {
+    fn insert(&mut self, value: T) -> usize {
+        if let Some(h) = self.free.pop() {
+            self.values[h] = Some(value);
+            h
+        } else {
+            self.values.push(Some(value));
+            self.values.len() - 1
+        }
+    }
+
+    fn take(&mut self, h: usize) -> Option<T> {
+        let value = self.values.get_mut(h)?.take()?;
+        self.free.push(h);
+        Some(value)
+    }
+
+    fn get(&self, h: usize) -> Option<&T> {
+        self.values.get(h)?.as_ref()
+    }
+}
+
+/// Square root decomposition: the sequence is a list of blocks of roughly sqrt(n) handles.
+/// Splits and removals leave small or empty blocks behind, so the blocks are rebuilt every
+/// O(sqrt(n)) operations to keep their number O(sqrt(n)).
+pub struct BlockList<T> {
+    slab: HandleSlab<T>,
+    blocks: Vec<Vec<usize>>,
+    order: Vec<usize>,
+    block_of: Vec<usize>,
+    len: usize,
+    /// Operations since the last rebuild.
+    ops: usize,
+}
+
+impl<T> Default for BlockList<T> {
+    fn default() -> Self {
+        Self {
+            slab: HandleSlab::default(),
+            blocks: Vec::new(),
+            order: Vec::new(),
+            block_of: Vec::new(),
+            len: 0,
+            ops: 0,
+        }
+    }
+}
+
+impl<T> BlockList<T> {
+    // Scanning a block is a fast linear search while walking the blocks chases pointers, so
+    // blocks are allowed a few times sqrt(n) handles.
+    fn max_block_len(&self) -> usize {
+        8 * (self.len as f64).sqrt().max(16.0) as usize
+    }
+
+    // Returns (index in order, index in block) of the given rank.
+    fn locate(&self, mut rank: usize) -> (usize, usize) {
+        for (i, b) in self.order.iter().enumerate() {
+            if rank < self.blocks[*b].len() {
+                return (i, rank);
+            }
+            rank -= self.blocks[*b].len();
+        }
+        (self.order.len(), rank)
+    }
+
+    fn attach(&mut self, h: usize, rank: usize) {
+        let (mut oi, mut bi) = self.locate(rank);
+        if oi == self.order.len() {
+            // Append to the last block, creating one if necessary.
+            if self.order.is_empty() {
+                self.order.push(self.blocks.len());
+                self.blocks.push(Vec::new());
+            }
+            oi = self.order.len() - 1;
+            bi = self.blocks[self.order[oi]].len();
+        }
+
+        let b = self.order[oi];
+        self.blocks[b].insert(bi, h);
+        self.block_of[h] = b;
+        self.len += 1;
+
+        if self.blocks[b].len() > self.max_block_len() {
+            let half = self.blocks[b].len() / 2;
+            let tail = self.blocks[b].split_off(half);
+            for t in &tail {
+                self.block_of[*t] = self.blocks.len();
+            }
+            self.order.insert(oi + 1, self.blocks.len());
+            self.blocks.push(tail);
+        }
+    }
+
+    /// Regroups the handles in order into blocks of half the maximum length.
+    fn rebuild(&mut self) {
+        let handles = self.order.iter().flat_map(|b| &self.blocks[*b]);
+        let handles = handles.copied().collect::<Vec<_>>();
+        let block_len = self.max_block_len() / 2;
+        self.blocks = handles.chunks(block_len).map(<[usize]>::to_vec).collect();
+        self.order = (0..self.blocks.len()).collect();
+        for (b, block) in self.blocks.iter().enumerate() {
+            for h in block {
+                self.block_of[*h] = b;
+            }
+        }
+        self.ops = 0;
+    }
+
+    fn count_op(&mut self) {
+        self.ops += 1;
+        if self.ops >= self.max_block_len() {
+            self.rebuild();
+        }
+    }
+
+    fn detach(&mut self, h: usize) -> Option<usize> {
+        self.slab.get(h)?;
+        let b = self.block_of[h];
+        let oi = self.order.iter().position(|o| *o == b)?;
+        let bi = self.blocks[b].iter().position(|x| *x == h)?;
+        let before: usize = self.order[..oi].iter().map(|o| self.blocks[*o].len()).sum();
+        self.blocks[b].remove(bi);
+        if self.blocks[b].is_empty() {
+            // The block slot itself stays allocated, only unlinked from the order.
+            self.order.remove(oi);
+        }
+        self.len -= 1;
+        Some(before + bi)
+    }
+}
+
+impl<T> PositionalSequence for BlockList<T> {
+    type Item = T;
+    type Handle = usize;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn insert_at(&mut self, value: T, rank: usize) -> usize {
+        let h = self.slab.insert(value);
+        if h == self.block_of.len() {
+            self.block_of.push(0);
+        }
+        self.attach(h, rank);
+        self.count_op();
+        h
+    }
+
+    fn remove(&mut self, handle: usize) -> Option<(T, usize)> {
+        let rank = self.detach(handle)?;
+        self.count_op();
+        Some((self.slab.take(handle)?, rank))
+    }
+
+    fn move_to(&mut self, handle: usize, new_rank: usize) -> Option<usize> {
+        let rank = self.detach(handle)?;
+        self.attach(handle, new_rank);
+        self.count_op();
+        Some(rank)
+    }
+
+    fn rank_of(&self, handle: usize) -> Option<usize> {
+        self.slab.get(handle)?;
+        let b = self.block_of[handle];
+        let mut rank = 0;
+        for o in &self.order {
+            if *o == b {
+                return Some(rank + self.blocks[b].iter().position(|x| *x == handle)?);
+            }
+            rank += self.blocks[*o].len();
+        }
+        None
+    }
+
+    fn get(&self, handle: usize) -> Option<&T> {
+        self.slab.get(handle)
+    }
+
+    fn get_at(&self, rank: usize) -> Option<&T> {
+        let (oi, bi) = self.locate(rank);
+        self.slab.get(*self.blocks[*self.order.get(oi)?].get(bi)?)
+    }
+}
+
+struct Fenwick {
+    tree: Vec<i32>,
+}
+
+impl Fenwick {
+    fn new(n: usize) -> Self {
+        Self {
+            tree: vec![0; n + 1],
+        }
+    }
+
+    fn add(&mut self, i: usize, delta: i32) {
+        let mut i = i + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    // Sum over [0, i).
+    fn prefix(&self, mut i: usize) -> usize {
+        let mut sum = 0;
+        while i > 0 {
+            sum += self.tree[i];
+            i &= i - 1;
+        }
+        sum as usize
+    }
+
+    // Smallest i such that prefix(i + 1) > k.
+    fn search(&self, mut k: usize) -> usize {
+        let mut pos = 0;
+        let mut step = (self.tree.len() - 1).next_power_of_two();
+        while step > 0 {
+            if pos + step < self.tree.len() && (self.tree[pos + step] as usize) <= k {
+                pos += step;
+                k -= self.tree[pos] as usize;
+            }
+            step /= 2;
+        }
+        pos
+    }
+}
+
+const EMPTY: usize = usize::MAX;
+
+/// A gapped array of slots with a Fenwick tree counting occupied slots. Inserting into a full
+/// neighborhood respreads the smallest enclosing aligned window that is sparse enough, like a
+/// packed-memory array.
+pub struct FenwickSlots<T> {
+    slab: HandleSlab<T>,
+    slots: Vec<usize>,
+    slot_of: Vec<usize>,
+    fen: Fenwick,
+    len: usize,
+}
+
+impl<T> Default for FenwickSlots<T> {
+    fn default() -> Self {
+        Self {
+            slab: HandleSlab::default(),
+            slots: vec![EMPTY; 64],
+            slot_of: Vec::new(),
+            fen: Fenwick::new(64),
+            len: 0,
+        }
+    }
+}
+
+impl<T> FenwickSlots<T> {
+    fn slot_at(&self, rank: usize) -> usize {
+        if rank < self.len {
+            self.fen.search(rank)
+        } else {
+            self.slots.len()
+        }
+    }
+
+    fn respread(&mut self, lo: usize, hi: usize, handles: &[usize]) {
+        for s in lo..hi {
+            if self.slots[s] != EMPTY {
+                self.slots[s] = EMPTY;
+                self.fen.add(s, -1);
+            }
+        }
+        for (i, h) in handles.iter().enumerate() {
+            let s = lo + i * (hi - lo) / handles.len();
+            self.slots[s] = *h;
+            self.slot_of[*h] = s;
+            self.fen.add(s, 1);
+        }
+    }
+
+    fn attach(&mut self, h: usize, rank: usize) {
+        let rank = rank.min(self.len);
+        let after = self.slot_at(rank);
+        let before = if rank > 0 {
+            Some(self.slot_at(rank - 1))
+        } else {
+            None
+        };
+        let first_free = before.map(|b| b + 1).unwrap_or(0);
+        self.len += 1;
+        if first_free < after {
+            let s = (first_free + after) / 2;
+            self.slots[s] = h;
+            self.slot_of[h] = s;
+            self.fen.add(s, 1);
+            return;
+        }
+
+        // Find the smallest aligned window around the insertion point that is sparse enough.
+        // The allowed density goes from full for the smallest windows down to half for the
+        // whole array, which keeps respreads amortized O(log^2 n).
+        let point = after.min(self.slots.len() - 1);
+        let height = (self.slots.len() / 64).trailing_zeros() as usize;
+        let (mut width, mut level) = (64, 0);
+        let (lo, hi) = loop {
+            if width >= self.slots.len() {
+                if 2 * self.len > self.slots.len() {
+                    self.grow();
+                }
+                break (0, self.slots.len());
+            }
+            let lo = point / width * width;
+            let hi = lo + width;
+            let count = self.fen.prefix(hi) - self.fen.prefix(lo);
+            if 2 * height * (count + 1) <= width * (2 * height - level) {
+                break (lo, hi);
+            }
+            (width, level) = (2 * width, level + 1);
+        };
+
+        let mut handles: Vec<usize> = Vec::with_capacity(hi - lo);
+        handles.extend(self.slots[lo..hi].iter().copied().filter(|x| *x != EMPTY));
+        handles.insert(rank.saturating_sub(self.fen.prefix(lo)), h);
+        self.respread(lo, hi, &handles);
+    }
+
+    fn grow(&mut self) {
+        let handles = self
+            .slots
+            .iter()
+            .copied()
+            .filter(|x| *x != EMPTY)
+            .collect::<Vec<_>>();
+        let n = 2 * self.slots.len();
+        self.slots = vec![EMPTY; n];
+        self.fen = Fenwick::new(n);
+        for (i, h) in handles.iter().enumerate() {
+            let s = i * n / handles.len();
+            self.slots[s] = *h;
+            self.slot_of[*h] = s;
+            self.fen.add(s, 1);
+        }
+    }
+
+    fn detach(&mut self, h: usize) -> Option<usize> {
+        self.slab.get(h)?;
+        let s = self.slot_of[h];
+        self.slots[s] = EMPTY;
+        self.fen.add(s, -1);
+        self.len -= 1;
+        Some(self.fen.prefix(s))
+    }
+}
+
+impl<T> PositionalSequence for FenwickSlots<T> {
+    type Item = T;
+    type Handle = usize;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn insert_at(&mut self, value: T, rank: usize) -> usize {
+        let h = self.slab.insert(value);
+        if h == self.slot_of.len() {
+            self.slot_of.push(EMPTY);
+        }
+        self.attach(h, rank);
+        h
+    }
+
+    fn remove(&mut self, handle: usize) -> Option<(T, usize)> {
+        let rank = self.detach(handle)?;
+        Some((self.slab.take(handle)?, rank))
+    }
+
+    fn move_to(&mut self, handle: usize, new_rank: usize) -> Option<usize> {
+        let rank = self.detach(handle)?;
+        self.attach(handle, new_rank);
+        Some(rank)
+    }
+
+    fn rank_of(&self, handle: usize) -> Option<usize> {
+        self.slab.get(handle)?;
+        Some(self.fen.prefix(self.slot_of[handle]))
+    }
+
+    fn get(&self, handle: usize) -> Option<&T> {
+        self.slab.get(handle)
+    }
+
+    fn get_at(&self, rank: usize) -> Option<&T> {
+        if rank >= self.len {
+            return None;
+        }
+        self.slab.get(self.slots[self.fen.search(rank)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn check_against_model<S: PositionalSequence<Item = u32>>(seq: &S, model: &[(u32, S::Handle)]) {
+        assert_eq!(seq.len(), model.len());
+        for (rank, (value, handle)) in model.iter().enumerate() {
+            assert_eq!(seq.get(*handle), Some(value));
+            assert_eq!(seq.get_at(rank), Some(value));
+            assert_eq!(seq.rank_of(*handle), Some(rank));
+        }
+        assert_eq!(seq.get_at(model.len()), None);
+    }
+
+    fn random_operations<S: PositionalSequence<Item = u32> + Default>() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut seq = S::default();
+            let mut model = Vec::new();
+            for step in 0..3000 {
+                let op = rng.gen_range(0..5);
+                if op < 2 || model.is_empty() {
+                    let rank = rng.gen_range(0..=model.len());
+                    model.insert(rank, (step, seq.insert_at(step, rank)));
+                } else if op == 2 {
+                    let rank = rng.gen_range(0..model.len());
+                    let (value, handle) = model.remove(rank);
+                    assert_eq!(seq.remove(handle), Some((value, rank)));
+                    // The handle is stale until a later insert reuses it.
+                    assert_eq!(seq.get(handle), None);
+                    assert_eq!(seq.rank_of(handle), None);
+                    assert_eq!(seq.move_to(handle, 0), None);
+                    assert_eq!(seq.remove(handle), None);
+                } else {
+                    let rank = rng.gen_range(0..model.len());
+                    let new_rank = rng.gen_range(0..model.len());
+                    let entry = model.remove(rank);
+                    model.insert(new_rank, entry);
+                    assert_eq!(seq.move_to(entry.1, new_rank), Some(rank));
+                }
+                if step % 100 == 0 {
+                    check_against_model(&seq, &model);
+                }
+            }
+            check_against_model(&seq, &model);
+        }
+    }
+
+    #[test]
+    fn treap_random_operations() {
+        random_operations::<Treap<u32>>();
+    }
+
+    #[test]
+    fn block_list_random_operations() {
+        random_operations::<BlockList<u32>>();
+    }
+
+    #[test]
+    fn fenwick_slots_random_operations() {
+        random_operations::<FenwickSlots<u32>>();
+    }
+}
@@ -1,4 +1,6 @@
+use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::ops::{Bound, Index, IndexMut, RangeBounds};
 
 use rand::Rng;
 use slotmap::{new_key_type, Key, SlotMap};
@@ -78,11 +80,89 @@ impl<T> Treap<T> {
         }
     }
 
+    fn leftmost(&self, mut node: NodeKey) -> NodeKey {
+        while let Some(n) = self.nm.get(node) {
+            if n.left.is_null() {
+                break;
+            }
+            node = n.left;
+        }
+        node
+    }
+
+    fn successor(&self, node: NodeKey) -> NodeKey {
+        let n = &self.nm[node];
+        if !n.right.is_null() {
+            return self.leftmost(n.right);
+        }
+
+        let mut prev = node;
+        let mut cur = n.parent;
+        while let Some(c) = self.nm.get(cur) {
+            if prev == c.left {
+                return cur;
+            }
+            (prev, cur) = (cur, c.parent);
+        }
+        NodeKey::null()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.count(self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_null()
+    }
+
     #[inline]
     pub fn get(&self, node: NodeKey) -> Option<&T> {
         Some(&self.nm.get(node)?.value)
     }
 
+    #[inline]
+    pub fn get_mut(&mut self, node: NodeKey) -> Option<&mut T> {
+        Some(&mut self.nm.get_mut(node)?.value)
+    }
+
+    pub fn get_by_rank(&self, rank: usize) -> Option<&T> {
+        self.get(self.derank(rank))
+    }
+
+    pub fn get_by_rank_mut(&mut self, rank: usize) -> Option<&mut T> {
+        self.get_mut(self.derank(rank))
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(self.leftmost(self.root))
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get_by_rank(self.len().checked_sub(1)?)
+    }
+
+    /// Swaps the values stored at two nodes, leaving the tree structure untouched. Returns
+    /// false if either node is not in the treap.
+    pub fn swap_values(&mut self, a: NodeKey, b: NodeKey) -> bool {
+        if a == b {
+            return self.nm.contains_key(a);
+        }
+        match self.nm.get_disjoint_mut([a, b]) {
+            Some([x, y]) => {
+                std::mem::swap(&mut x.value, &mut y.value);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Replaces the value stored at a node, returning the old value.
+    pub fn replace(&mut self, node: NodeKey, value: T) -> Option<T> {
+        Some(std::mem::replace(self.get_mut(node)?, value))
+    }
+
     pub fn rank(&self, node: NodeKey) -> Option<usize> {
         let n = self.nm.get(node)?;
         let mut rank = self.count(n.left);
@@ -114,28 +194,21 @@ impl<T> Treap<T> {
         cur
     }
 
-    pub fn insert<R: Rng>(&mut self, value: T, rank: usize, rng: &mut R) -> NodeKey {
+    fn attach(&mut self, node: NodeKey, rank: usize) {
         let (l, r) = self.split(self.root, rank);
-        let m = self.nm.insert(TreapNode {
-            value,
-            priority: rng.gen(),
-            left: NodeKey::null(),
-            right: NodeKey::null(),
-            parent: NodeKey::null(),
-            count: 1,
-        });
-        let lm = self.merge(l, m);
+        let lm = self.merge(l, node);
         self.root = self.merge(lm, r);
         self.nm[self.root].parent = NodeKey::null();
-        m
     }
 
-    pub fn remove(&mut self, node: NodeKey) -> Option<(T, usize)> {
-        let r = self.nm.remove(node)?;
+    // Unlinks node from the tree, leaving it as a lone node in the map. Returns its old rank.
+    fn detach(&mut self, node: NodeKey) -> Option<usize> {
+        let n = self.nm.get(node)?;
+        let (left, right, parent) = (n.left, n.right, n.parent);
 
         // Compute rank and update parent counts.
-        let mut rank = self.count(r.left);
-        let mut cur = r.parent;
+        let mut rank = self.count(left);
+        let mut cur = parent;
         let mut prev = node;
         while let Some(c) = self.nm.get_mut(cur) {
             let (l, r, p) = (c.left, c.right, c.parent);
@@ -147,11 +220,11 @@ impl<T> Treap<T> {
         }
 
         // Update parent pointers / pointers in parent.
-        let merged = self.merge(r.left, r.right);
+        let merged = self.merge(left, right);
         if let Some(m) = self.nm.get_mut(merged) {
-            m.parent = r.parent;
+            m.parent = parent;
         }
-        if let Some(p) = self.nm.get_mut(r.parent) {
+        if let Some(p) = self.nm.get_mut(parent) {
             if p.left == node {
                 p.left = merged;
             } else {
@@ -161,6 +234,559 @@ impl<T> Treap<T> {
             self.root = merged;
         }
 
-        Some((r.value, rank))
+        let n = &mut self.nm[node];
+        (n.left, n.right, n.parent, n.count) =
+            (NodeKey::null(), NodeKey::null(), NodeKey::null(), 1);
+        Some(rank)
+    }
+
+    pub fn insert<R: Rng>(&mut self, value: T, rank: usize, rng: &mut R) -> NodeKey {
+        let m = self.nm.insert(TreapNode {
+            value,
+            priority: rng.gen(),
+            left: NodeKey::null(),
+            right: NodeKey::null(),
+            parent: NodeKey::null(),
+            count: 1,
+        });
+        self.attach(m, rank);
+        m
+    }
+
+    pub fn remove(&mut self, node: NodeKey) -> Option<(T, usize)> {
+        let rank = self.detach(node)?;
+        Some((self.nm.remove(node)?.value, rank))
+    }
+
+    /// Moves node to rank `new_rank` (counted after its removal), keeping its key, value and
+    /// priority. Returns the old rank.
+    pub fn move_to(&mut self, node: NodeKey, new_rank: usize) -> Option<usize> {
+        let rank = self.detach(node)?;
+        self.attach(node, new_rank);
+        Some(rank)
+    }
+
+    /// Iterates over the values with ranks in `start..end`, in order.
+    pub fn iter_ranks(&self, start: usize, end: usize) -> Iter<'_, T> {
+        let end = end.min(self.len());
+        Iter {
+            trp: self,
+            cur: self.derank(start),
+            remaining: end.saturating_sub(start),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_ranks(0, self.len())
+    }
+
+    /// Returns the number of leading values for which `pred` holds, assuming the treap is
+    /// partitioned according to `pred` (all true values come before all false values).
+    pub fn partition_point<P: FnMut(&T) -> bool>(&self, mut pred: P) -> usize {
+        let mut rank = 0;
+        let mut cur = self.root;
+        while let Some(c) = self.nm.get(cur) {
+            if pred(&c.value) {
+                rank += self.count(c.left) + 1;
+                cur = c.right;
+            } else {
+                cur = c.left;
+            }
+        }
+        rank
+    }
+
+    fn check_subtree(&self, node: NodeKey, parent: NodeKey) -> usize {
+        let Some(n) = self.nm.get(node) else {
+            return 0;
+        };
+        assert!(n.parent == parent, "bad parent link");
+        if let Some(p) = self.nm.get(parent) {
+            assert!(p.priority <= n.priority, "heap order violated");
+        }
+        let count = 1 + self.check_subtree(n.left, node) + self.check_subtree(n.right, node);
+        assert!(n.count == count, "bad subtree count");
+        count
+    }
+
+    /// Verifies heap order, subtree counts and parent links, panicking if any are violated.
+    pub fn check_invariants(&self) {
+        let reachable = self.check_subtree(self.root, NodeKey::null());
+        assert!(reachable == self.nm.len(), "unreachable nodes");
+    }
+}
+
+/// A treap kept in sorted order. Only operations that preserve the order are exposed, so the
+/// binary searches over the values stay valid. Equal values keep their insertion order. With
+/// `KeyValue` entries it is an ordered map with order statistics.
+pub struct OrderedTreap<T> {
+    trp: Treap<T>,
+}
+
+impl<T> Default for OrderedTreap<T> {
+    fn default() -> Self {
+        // Not `Treap::default()`, whose derive requires `T: Default`.
+        let trp = Treap {
+            nm: SlotMap::with_key(),
+            root: NodeKey::null(),
+        };
+        Self { trp }
+    }
+}
+
+impl<T: Ord> OrderedTreap<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trp.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trp.is_empty()
+    }
+
+    pub fn get_node(&self, node: NodeKey) -> Option<&T> {
+        self.trp.get(node)
+    }
+
+    pub fn rank(&self, node: NodeKey) -> Option<usize> {
+        self.trp.rank(node)
+    }
+
+    /// The first value equal to `key`.
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+    {
+        self.select(self.rank_of_key(key)?)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.trp.first()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.trp.last()
+    }
+
+    /// Inserts `value` after all values that compare less than or equal to it.
+    pub fn insert_by_key<R: Rng>(&mut self, value: T, rng: &mut R) -> NodeKey {
+        let rank = self.upper_bound(&value);
+        self.trp.insert(value, rank, rng)
+    }
+
+    pub fn remove(&mut self, node: NodeKey) -> Option<(T, usize)> {
+        self.trp.remove(node)
+    }
+
+    /// Removes the first value equal to `key`.
+    pub fn remove_by_key<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+    {
+        let node = self.trp.derank(self.rank_of_key(key)?);
+        self.trp.remove(node).map(|(value, _)| value)
+    }
+
+    /// Rank of the first value that is not less than `key`.
+    pub fn lower_bound<Q: Ord + ?Sized>(&self, key: &Q) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        self.trp.partition_point(|v| v.borrow() < key)
+    }
+
+    /// Rank of the first value that is greater than `key`.
+    pub fn upper_bound<Q: Ord + ?Sized>(&self, key: &Q) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        self.trp.partition_point(|v| v.borrow() <= key)
+    }
+
+    /// Rank of the first value equal to `key`, if any.
+    pub fn rank_of_key<Q: Ord + ?Sized>(&self, key: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+    {
+        let rank = self.lower_bound(key);
+        let found = self.select(rank)?;
+        (found.borrow() == key).then_some(rank)
+    }
+
+    /// Returns the k-th smallest value (zero-indexed).
+    pub fn select(&self, k: usize) -> Option<&T> {
+        self.trp.get_by_rank(k)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.trp.iter()
+    }
+
+    pub fn range<Q: Ord + ?Sized, B: RangeBounds<Q>>(&self, range: B) -> Iter<'_, T>
+    where
+        T: Borrow<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(k) => self.lower_bound(k),
+            Bound::Excluded(k) => self.upper_bound(k),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(k) => self.upper_bound(k),
+            Bound::Excluded(k) => self.lower_bound(k),
+            Bound::Unbounded => self.len(),
+        };
+        self.trp.iter_ranks(start, end)
+    }
+
+    /// Like `Treap::check_invariants`, and also checks that the values are sorted.
+    pub fn check_invariants(&self) {
+        self.trp.check_invariants();
+        let values = self.iter().collect::<Vec<_>>();
+        assert!(
+            values.windows(2).all(|w| w[0] <= w[1]),
+            "values out of order"
+        );
+    }
+}
+
+/// Map entry for an `OrderedTreap`, ordered and compared by its key only.
+#[derive(Clone, Debug)]
+pub struct KeyValue<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K: Ord, V> Ord for KeyValue<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<K: Ord, V> PartialOrd for KeyValue<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Eq, V> PartialEq for KeyValue<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Eq, V> Eq for KeyValue<K, V> {}
+
+impl<K, V> Borrow<K> for KeyValue<K, V> {
+    fn borrow(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Ord, V> OrderedTreap<KeyValue<K, V>> {
+    /// Sets the value of `key`, returning the previous one if the key was present.
+    pub fn insert<R: Rng>(&mut self, key: K, value: V, rng: &mut R) -> Option<V> {
+        match self.get_mut(&key) {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                self.insert_by_key(KeyValue { key, value }, rng);
+                None
+            },
+        }
+    }
+
+    /// The value of `key`, which can be changed in place as it doesn't affect the order.
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        let rank = self.rank_by(|k| k.borrow().cmp(key))?;
+        self.trp.get_by_rank_mut(rank).map(|entry| &mut entry.value)
+    }
+
+    fn rank_by(&self, cmp: impl Fn(&K) -> Ordering) -> Option<usize> {
+        let rank = self.trp.partition_point(|e| cmp(&e.key) == Ordering::Less);
+        let found = self.trp.get_by_rank(rank)?;
+        (cmp(&found.key) == Ordering::Equal).then_some(rank)
+    }
+}
+
+impl<T> Index<NodeKey> for Treap<T> {
+    type Output = T;
+
+    fn index(&self, node: NodeKey) -> &T {
+        &self.nm[node].value
+    }
+}
+
+impl<T> IndexMut<NodeKey> for Treap<T> {
+    fn index_mut(&mut self, node: NodeKey) -> &mut T {
+        &mut self.nm[node].value
+    }
+}
+
+pub struct Iter<'a, T> {
+    trp: &'a Treap<T>,
+    cur: NodeKey,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.cur;
+        self.remaining -= 1;
+        self.cur = self.trp.successor(node);
+        self.trp.get(node)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn check_against_model(trp: &Treap<u32>, model: &[(u32, NodeKey)]) {
+        trp.check_invariants();
+        assert_eq!(trp.len(), model.len());
+        assert!(trp.iter().eq(model.iter().map(|(v, _)| v)));
+        for (r, (v, k)) in model.iter().enumerate() {
+            assert_eq!(trp.rank(*k), Some(r));
+            assert_eq!(trp.derank(r), *k);
+            assert_eq!(trp.get(*k), Some(v));
+        }
+        assert!(trp.derank(model.len()).is_null());
+    }
+
+    #[test]
+    fn random_operations() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut trp = Treap::default();
+            let mut model = Vec::new();
+            for step in 0..1000 {
+                let op = rng.gen_range(0..4);
+                if op < 2 || model.is_empty() {
+                    let rank = rng.gen_range(0..=model.len());
+                    model.insert(rank, (step, trp.insert(step, rank, &mut rng)));
+                } else if op == 2 {
+                    let rank = rng.gen_range(0..model.len());
+                    let (v, k) = model.remove(rank);
+                    assert_eq!(trp.remove(k), Some((v, rank)));
+                    assert_eq!(trp.get(k), None);
+                    assert_eq!(trp.remove(k), None);
+                } else {
+                    let rank = rng.gen_range(0..model.len());
+                    let new_rank = rng.gen_range(0..model.len());
+                    let entry = model.remove(rank);
+                    model.insert(new_rank, entry);
+                    assert_eq!(trp.move_to(entry.1, new_rank), Some(rank));
+                }
+                trp.check_invariants();
+                if step % 50 == 0 {
+                    check_against_model(&trp, &model);
+                }
+            }
+            check_against_model(&trp, &model);
+        }
+    }
+
+    #[test]
+    fn remove_root() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut trp = Treap::default();
+        let mut model = (0..100)
+            .map(|i| (i, trp.insert(i, i as usize, &mut rng)))
+            .collect::<Vec<_>>();
+        while !model.is_empty() {
+            let root_rank = trp.rank(trp.root).unwrap();
+            let (v, k) = model.remove(root_rank);
+            assert_eq!(trp.remove(k), Some((v, root_rank)));
+            check_against_model(&trp, &model);
+        }
+        assert!(trp.is_empty());
+    }
+
+    #[test]
+    fn single_node() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut trp = Treap::default();
+        let k = trp.insert(42, 0, &mut rng);
+        trp.check_invariants();
+        assert_eq!(trp.move_to(k, 0), Some(0));
+        assert_eq!(trp.remove(k), Some((42, 0)));
+        trp.check_invariants();
+        assert!(trp.is_empty());
+        assert!(trp.derank(0).is_null());
+
+        // The treap must remain usable after becoming empty.
+        let k = trp.insert(7, 0, &mut rng);
+        check_against_model(&trp, &[(7, k)]);
+    }
+
+    #[test]
+    fn value_access() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut trp = Treap::default();
+        assert_eq!(trp.first(), None);
+        assert_eq!(trp.last(), None);
+        let keys = (0..10)
+            .map(|i| trp.insert(i, i as usize, &mut rng))
+            .collect::<Vec<_>>();
+        assert_eq!((trp.first(), trp.last()), (Some(&0), Some(&9)));
+
+        trp[keys[3]] += 100;
+        *trp.get_by_rank_mut(4).unwrap() += 100;
+        assert_eq!(trp.get_by_rank(3), Some(&103));
+        assert_eq!(trp[keys[4]], 104);
+        assert!(trp.swap_values(keys[0], keys[9]));
+        assert!(trp.swap_values(keys[5], keys[5]));
+        assert_eq!(trp.replace(keys[1], 50), Some(1));
+        assert!(trp.iter().copied().eq([9, 50, 2, 103, 104, 5, 6, 7, 8, 0]));
+
+        let (v, _) = trp.remove(keys[2]).unwrap();
+        assert_eq!(v, 2);
+        assert!(!trp.swap_values(keys[2], keys[3]));
+        assert_eq!(trp.replace(keys[2], 0), None);
+        trp.check_invariants();
+    }
+
+    #[test]
+    fn derank_past_end() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut trp = Treap::default();
+        assert!(trp.derank(0).is_null());
+        for i in 0..10 {
+            trp.insert(i, i as usize, &mut rng);
+        }
+        for rank in [10, 11, 1000, usize::MAX] {
+            assert!(trp.derank(rank).is_null());
+            assert_eq!(trp.get(trp.derank(rank)), None);
+        }
+        assert_eq!(trp.iter_ranks(8, 1000).count(), 2);
+        assert_eq!(trp.iter_ranks(1000, 2000).count(), 0);
+    }
+
+    #[test]
+    fn ordered_against_sorted_vec() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut trp = OrderedTreap::new();
+        // (value, insertion step) pairs, sorted by value then step.
+        let mut model: Vec<(u32, NodeKey)> = Vec::new();
+        for step in 0..2000 {
+            if rng.gen_range(0..3) < 2 || model.is_empty() {
+                // Few distinct values, so there are many duplicates.
+                let value = rng.gen_range(0..50);
+                let key = trp.insert_by_key(value, &mut rng);
+                let rank = model.partition_point(|(v, _)| *v <= value);
+                assert_eq!(trp.rank(key), Some(rank));
+                model.insert(rank, (value, key));
+            } else {
+                let rank = rng.gen_range(0..model.len());
+                let (value, key) = model.remove(rank);
+                assert_eq!(trp.remove(key), Some((value, rank)));
+            }
+            if step % 50 != 0 {
+                continue;
+            }
+
+            trp.check_invariants();
+            let values = model.iter().map(|(v, _)| *v).collect::<Vec<_>>();
+            assert!(trp.iter().eq(&values));
+            assert_eq!(trp.first(), values.first());
+            assert_eq!(trp.last(), values.last());
+            for key in 0..52 {
+                let lower = values.partition_point(|v| *v < key);
+                let upper = values.partition_point(|v| *v <= key);
+                assert_eq!(trp.lower_bound(&key), lower);
+                assert_eq!(trp.upper_bound(&key), upper);
+                assert_eq!(trp.rank_of_key(&key), (lower < upper).then_some(lower));
+                assert_eq!(trp.select(lower), values.get(lower));
+
+                let hi = key + rng.gen_range(0..10);
+                let expected = |range: (Bound<u32>, Bound<u32>)| {
+                    values
+                        .iter()
+                        .filter(|v| range.contains(v))
+                        .copied()
+                        .collect::<Vec<_>>()
+                };
+                let bounds = [
+                    (Bound::Included(key), Bound::Included(hi)),
+                    (Bound::Included(key), Bound::Excluded(hi)),
+                    (Bound::Excluded(key), Bound::Included(hi)),
+                    (Bound::Excluded(key), Bound::Excluded(hi)),
+                    (Bound::Unbounded, Bound::Excluded(hi)),
+                    (Bound::Included(key), Bound::Unbounded),
+                    (Bound::Unbounded, Bound::Unbounded),
+                ];
+                for range in bounds {
+                    let found = trp.range(range).copied().collect::<Vec<_>>();
+                    assert_eq!(found, expected(range), "range {range:?}");
+                }
+                assert!(trp
+                    .range(key..hi)
+                    .copied()
+                    .eq(expected((Bound::Included(key), Bound::Excluded(hi)))));
+                assert!(trp
+                    .range(key..=hi)
+                    .eq(trp.range((Bound::Included(key), Bound::Included(hi)))));
+            }
+        }
+    }
+
+    #[test]
+    fn map_against_btreemap() {
+        let mut rng = StdRng::seed_from_u64(26);
+        let mut trp = OrderedTreap::new();
+        let mut model = std::collections::BTreeMap::new();
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..64u32);
+            match rng.gen_range(0..4) {
+                0 => {
+                    let value: u32 = rng.gen();
+                    assert_eq!(trp.insert(key, value, &mut rng), model.insert(key, value));
+                },
+                1 => {
+                    let removed = trp.remove_by_key(&key).map(|e: KeyValue<_, _>| e.value);
+                    assert_eq!(removed, model.remove(&key));
+                },
+                2 => {
+                    if let Some(value) = trp.get_mut(&key) {
+                        *value ^= key;
+                    }
+                    if let Some(value) = model.get_mut(&key) {
+                        *value ^= key;
+                    }
+                },
+                _ => {},
+            }
+            trp.check_invariants();
+            assert_eq!(trp.len(), model.len());
+            assert_eq!(trp.get(&key).map(|e| e.value), model.get(&key).copied());
+            assert_eq!(
+                trp.rank_of_key(&key),
+                model.contains_key(&key).then(|| model.range(..key).count())
+            );
+            assert!(trp
+                .range(key..)
+                .map(|e| (e.key, e.value))
+                .eq(model.range(key..).map(|(k, v)| (*k, *v))));
+        }
     }
 }
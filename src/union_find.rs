@@ -0,0 +1,325 @@
+use std::hash::Hash;
+
+use hashbrown::HashMap;
+
+/// Disjoint sets over the elements 0..len, with path compression and union by size.
+#[derive(Clone, Debug, Default)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    num_components: usize,
+}
+
+impl UnionFind {
+    /// `n` singleton sets.
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            num_components: n,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Adds a new singleton set and returns its element.
+    pub fn add(&mut self) -> usize {
+        let x = self.parent.len();
+        self.parent.push(x);
+        self.size.push(1);
+        self.num_components += 1;
+        x
+    }
+
+    /// The representative of the set containing `x`.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut cur = x;
+        while self.parent[cur] != root {
+            let next = self.parent[cur];
+            self.parent[cur] = root;
+            cur = next;
+        }
+        root
+    }
+
+    /// Merges the sets containing `a` and `b`, returning false if they were already merged.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            (a, b) = (b, a);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.num_components -= 1;
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Size of the set containing `x`.
+    pub fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    pub fn num_components(&self) -> usize {
+        self.num_components
+    }
+
+    /// The representative of every set.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(|&x| self.parent[x] == x)
+    }
+
+    /// The sizes of all sets, in the order of `roots`.
+    pub fn component_sizes(&self) -> Vec<usize> {
+        self.roots().map(|root| self.size[root]).collect()
+    }
+
+    /// The elements of each set, in the order of `roots` and increasing within a set.
+    pub fn components(&mut self) -> Vec<Vec<usize>> {
+        let mut index = vec![usize::MAX; self.len()];
+        let mut components = Vec::with_capacity(self.num_components);
+        for root in self.roots() {
+            index[root] = components.len();
+            components.push(Vec::with_capacity(self.size[root]));
+        }
+        for x in 0..self.len() {
+            let root = self.find(x);
+            components[index[root]].push(x);
+        }
+        components
+    }
+}
+
+/// `UnionFind` over hashable keys, which are added on first use.
+#[derive(Clone, Debug)]
+pub struct KeyedUnionFind<K> {
+    ids: HashMap<K, usize>,
+    keys: Vec<K>,
+    sets: UnionFind,
+}
+
+impl<K> Default for KeyedUnionFind<K> {
+    fn default() -> Self {
+        Self {
+            ids: HashMap::new(),
+            keys: Vec::new(),
+            sets: UnionFind::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> KeyedUnionFind<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.ids.contains_key(key)
+    }
+
+    /// Adds `key` as a singleton set if absent, returning its element in `sets()`.
+    pub fn insert(&mut self, key: K) -> usize {
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+        let id = self.sets.add();
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key);
+        id
+    }
+
+    /// The element of `key` in `sets()`.
+    pub fn id(&self, key: &K) -> Option<usize> {
+        self.ids.get(key).copied()
+    }
+
+    pub fn key(&self, id: usize) -> &K {
+        &self.keys[id]
+    }
+
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// The underlying sets over element ids.
+    pub fn sets(&mut self) -> &mut UnionFind {
+        &mut self.sets
+    }
+
+    /// The representative key of the set containing `key`.
+    pub fn find(&mut self, key: &K) -> Option<&K> {
+        let root = self.sets.find(self.id(key)?);
+        Some(&self.keys[root])
+    }
+
+    /// Merges the sets containing `a` and `b`, adding them if needed. Returns false if they
+    /// were already merged.
+    pub fn union(&mut self, a: K, b: K) -> bool {
+        let (a, b) = (self.insert(a), self.insert(b));
+        self.sets.union(a, b)
+    }
+
+    /// Whether both keys are present and in the same set.
+    pub fn same(&mut self, a: &K, b: &K) -> bool {
+        match (self.id(a), self.id(b)) {
+            (Some(a), Some(b)) => self.sets.same(a, b),
+            _ => false,
+        }
+    }
+
+    /// Size of the set containing `key`, zero if absent.
+    pub fn size(&mut self, key: &K) -> usize {
+        self.id(key).map_or(0, |id| self.sets.size(id))
+    }
+
+    pub fn num_components(&self) -> usize {
+        self.sets.num_components()
+    }
+
+    pub fn component_sizes(&self) -> Vec<usize> {
+        self.sets.component_sizes()
+    }
+
+    /// The keys of each set, in insertion order within a set.
+    pub fn components(&mut self) -> Vec<Vec<&K>> {
+        let components = self.sets.components();
+        let to_keys = |ids: Vec<usize>| ids.into_iter().map(|id| &self.keys[id]).collect();
+        components.into_iter().map(to_keys).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// Labels every element with its set, relabelling a whole set on each union.
+    struct NaiveSets {
+        label: Vec<usize>,
+    }
+
+    impl NaiveSets {
+        fn union(&mut self, a: usize, b: usize) -> bool {
+            let (from, to) = (self.label[b], self.label[a]);
+            for l in &mut self.label {
+                if *l == from {
+                    *l = to;
+                }
+            }
+            from != to
+        }
+
+        fn size(&self, x: usize) -> usize {
+            self.label.iter().filter(|&&l| l == self.label[x]).count()
+        }
+
+        /// The sets ordered by smallest element.
+        fn components(&self) -> Vec<Vec<usize>> {
+            let groups = (0..self.label.len()).into_group_map_by(|&x| self.label[x]);
+            groups.into_values().sorted().collect()
+        }
+    }
+
+    fn check_against_model(sets: &mut UnionFind, model: &NaiveSets) {
+        let expected = model.components();
+        assert_eq!(sets.num_components(), expected.len());
+        assert_eq!(
+            sets.components().into_iter().sorted().collect_vec(),
+            expected
+        );
+        let sizes = sets.roots().map(|root| model.size(root)).collect_vec();
+        assert_eq!(sets.component_sizes(), sizes);
+        for x in 0..sets.len() {
+            assert_eq!(sets.size(x), model.size(x));
+            assert_eq!(model.label[sets.find(x)], model.label[x]);
+        }
+    }
+
+    #[test]
+    fn random_unions() {
+        let mut rng = StdRng::seed_from_u64(40);
+        for _ in 0..100 {
+            let n = rng.gen_range(1..30);
+            let mut sets = UnionFind::new(n);
+            let mut model = NaiveSets {
+                label: (0..n).collect(),
+            };
+            for _ in 0..60 {
+                if rng.gen_ratio(1, 10) {
+                    assert_eq!(sets.add(), model.label.len());
+                    model.label.push(model.label.len());
+                }
+                let (a, b) = (rng.gen_range(0..sets.len()), rng.gen_range(0..sets.len()));
+                assert_eq!(sets.same(a, b), model.label[a] == model.label[b]);
+                assert_eq!(sets.union(a, b), model.union(a, b));
+                assert!(sets.same(a, b));
+                check_against_model(&mut sets, &model);
+            }
+        }
+    }
+
+    #[test]
+    fn keyed_against_unkeyed() {
+        let mut rng = StdRng::seed_from_u64(40);
+        let mut keyed = KeyedUnionFind::new();
+        let mut sets = UnionFind::default();
+        let mut ids = HashMap::new();
+        for _ in 0..500 {
+            let [a, b] = [0; 2].map(|_| format!("k{}", rng.gen_range(0..50)));
+            for key in [&a, &b] {
+                if !ids.contains_key(key) {
+                    assert!(!keyed.contains(key));
+                    assert_eq!(keyed.size(key), 0);
+                    ids.insert(key.clone(), sets.add());
+                }
+            }
+            assert_eq!(keyed.same(&a, &b), sets.same(ids[&a], ids[&b]));
+            assert_eq!(
+                keyed.union(a.clone(), b.clone()),
+                sets.union(ids[&a], ids[&b])
+            );
+            assert_eq!(keyed.id(&a), Some(ids[&a]));
+            assert_eq!(keyed.key(ids[&b]), &b);
+            assert_eq!(keyed.size(&a), sets.size(ids[&a]));
+            let root = keyed.find(&a).cloned().unwrap();
+            assert_eq!(ids[&root], sets.find(ids[&a]));
+        }
+        assert_eq!(keyed.len(), ids.len());
+        assert_eq!(keyed.num_components(), sets.num_components());
+        let components = keyed.components();
+        let expected = sets.components();
+        assert_eq!(components.len(), expected.len());
+        for (keys, component) in components.iter().zip(expected) {
+            assert!(keys.iter().map(|&k| ids[k]).eq(component));
+        }
+        assert_eq!(keyed.find(&"missing".to_string()), None);
+    }
+}